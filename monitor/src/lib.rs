@@ -0,0 +1,228 @@
+
+// An interactive command monitor for the 6502 debugger, in the spirit of
+// the classic machine-language monitors: when the emulated machine halts
+// on a breakpoint or a step, it reads commands from stdin and applies them
+// to the running `Cpu`/`Bus` instead of hardcoding a `watch_memory_range`
+// closure in the frontend's `main`.
+
+mod disasm;
+
+use std::collections::HashSet;
+use std::io::{self, Write};
+
+use mos6502::cpu::{Cpu, Reg};
+use mos6502::memory::Bus;
+
+pub use disasm::disassemble;
+
+#[derive(Default)]
+pub struct Monitor {
+  breakpoints: HashSet<u16>,
+  trace_only: bool,
+  last_line: String,
+  // A one-shot breakpoint set by `next` to step over a `JSR`: the return
+  // address it's watching for, so the next `repl` entry clears it again
+  // (whether the stop was at that address or at another breakpoint)
+  // instead of leaving a stray breakpoint behind.
+  step_over_target: Option<u16>,
+}
+
+enum Command {
+  Break(u16),
+  Clear(u16),
+  Step(usize),
+  Next(usize),
+  Continue,
+  Reg(Option<(String, u16)>),
+  Mem(u16, u16),
+  Write(u16, u8),
+  Disasm(u16, usize),
+  Trace,
+  Help,
+  Unknown(String),
+}
+
+impl Monitor {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn add_breakpoint(&mut self, addr: u16) {
+    self.breakpoints.insert(addr);
+  }
+
+  pub fn remove_breakpoint(&mut self, addr: u16) {
+    self.breakpoints.remove(&addr);
+  }
+
+  pub fn has_breakpoint(&self, addr: u16) -> bool {
+    self.breakpoints.contains(&addr)
+  }
+
+  pub fn trace_only(&self) -> bool {
+    self.trace_only
+  }
+
+  /// Logs one executed instruction without stopping; used in trace-only
+  /// mode so a ROM can be followed without breaking the run.
+  pub fn trace(&self, cpu: &Cpu, bus: &dyn Bus) {
+    let (text, _len) = disassemble(cpu.pc(), bus);
+    println!("{:04X}  {}", cpu.pc(), text);
+  }
+
+  /// Runs the REPL until a `step`/`next`/`continue` command hands control
+  /// back to the emulator. Returns the number of instructions to execute
+  /// before re-entering the monitor (0 means "run until next breakpoint").
+  pub fn repl(&mut self, cpu: &mut Cpu, bus: &mut dyn Bus) -> usize {
+    if let Some(target) = self.step_over_target.take() {
+      self.breakpoints.remove(&target);
+    }
+
+    loop {
+      print!("({:04X}) monitor> ", cpu.pc());
+      io::stdout().flush().ok();
+
+      let mut line = String::new();
+      if io::stdin().read_line(&mut line).unwrap_or(0) == 0 {
+        return 0; // stdin closed, just free-run
+      }
+
+      let line = line.trim();
+      let line = if line.is_empty() { self.last_line.clone() } else { line.to_string() };
+      if line.is_empty() {
+        continue;
+      }
+      self.last_line = line.clone();
+
+      match Self::parse(&line) {
+        Command::Break(addr) => { self.add_breakpoint(addr); println!("breakpoint set at ${:04X}", addr); }
+        Command::Clear(addr) => { self.remove_breakpoint(addr); println!("breakpoint cleared at ${:04X}", addr); }
+        Command::Step(n) => return n,
+        Command::Next(n) => {
+          // Step over a `JSR` instead of into it: plant a one-shot
+          // breakpoint at the return address and free-run, rather than
+          // single-stepping through the whole subroutine.
+          if n == 1 {
+            if let Some(target) = Self::jsr_return_address(cpu, bus) {
+              self.breakpoints.insert(target);
+              self.step_over_target = Some(target);
+              return 0;
+            }
+          }
+          return n;
+        }
+        Command::Continue => return 0,
+        Command::Reg(None) => self.print_regs(cpu),
+        Command::Reg(Some((name, val))) => self.set_reg(cpu, &name, val),
+        Command::Mem(addr, len) => self.dump_mem(bus, addr, len),
+        Command::Write(addr, val) => { bus.write8(val, addr); println!("${:04X} = ${:02X}", addr, val); }
+        Command::Disasm(addr, count) => self.disasm(bus, addr, count),
+        Command::Trace => { self.trace_only = !self.trace_only; println!("trace-only: {}", self.trace_only); }
+        Command::Help => self.print_help(),
+        Command::Unknown(cmd) => println!("unknown command: {cmd} (try 'help')"),
+      }
+    }
+  }
+
+  fn parse(line: &str) -> Command {
+    let mut parts = line.split_whitespace();
+    let cmd = parts.next().unwrap_or("");
+    let rest: Vec<&str> = parts.collect();
+
+    match cmd {
+      "break" | "b" => rest.first().and_then(|a| parse_addr(a)).map(Command::Break).unwrap_or(Command::Unknown(line.into())),
+      "clear" => rest.first().and_then(|a| parse_addr(a)).map(Command::Clear).unwrap_or(Command::Unknown(line.into())),
+      "step" | "s" => Command::Step(rest.first().and_then(|n| n.parse().ok()).unwrap_or(1)),
+      "next" | "n" => Command::Next(rest.first().and_then(|n| n.parse().ok()).unwrap_or(1)),
+      "continue" | "c" => Command::Continue,
+      "reg" | "r" => {
+        if rest.len() >= 2 {
+          parse_addr(rest[1])
+            .map(|val| Command::Reg(Some((rest[0].to_uppercase(), val))))
+            .unwrap_or(Command::Unknown(line.into()))
+        } else {
+          Command::Reg(None)
+        }
+      }
+      "mem" | "m" => {
+        let addr = rest.first().and_then(|a| parse_addr(a));
+        let len = rest.get(1).and_then(|n| n.parse().ok()).unwrap_or(16);
+        addr.map(|a| Command::Mem(a, len)).unwrap_or(Command::Unknown(line.into()))
+      }
+      "write" | "w" => {
+        let addr = rest.first().and_then(|a| parse_addr(a));
+        let val = rest.get(1).and_then(|v| parse_addr(v)).map(|v| v as u8);
+        match (addr, val) {
+          (Some(a), Some(v)) => Command::Write(a, v),
+          _ => Command::Unknown(line.into()),
+        }
+      }
+      "disasm" | "d" => {
+        let addr = rest.first().and_then(|a| parse_addr(a));
+        let count = rest.get(1).and_then(|n| n.parse().ok()).unwrap_or(10);
+        addr.map(|a| Command::Disasm(a, count)).unwrap_or(Command::Unknown(line.into()))
+      }
+      "trace" | "t" => Command::Trace,
+      "help" | "h" | "?" => Command::Help,
+      other => Command::Unknown(other.into()),
+    }
+  }
+
+  fn print_regs(&self, cpu: &Cpu) {
+    println!(
+      "A:{:02X} X:{:02X} Y:{:02X} SP:{:02X} P:{:02X} PC:{:04X}",
+      cpu[Reg::AC], cpu[Reg::X], cpu[Reg::Y], cpu[Reg::SP], cpu.flags_as_byte(), cpu.pc()
+    );
+  }
+
+  fn set_reg(&self, cpu: &mut Cpu, name: &str, val: u16) {
+    match name {
+      "A" | "AC" => cpu[Reg::AC] = val as u8,
+      "X" => cpu[Reg::X] = val as u8,
+      "Y" => cpu[Reg::Y] = val as u8,
+      "SP" => cpu[Reg::SP] = val as u8,
+      "P" => cpu.set_flags_as_byte(val as u8),
+      "PC" => cpu.set_pc(val),
+      _ => { println!("unknown register: {name}"); return; }
+    }
+    self.print_regs(cpu);
+  }
+
+  fn dump_mem(&self, bus: &dyn Bus, addr: u16, len: u16) {
+    for row in (0..len).step_by(8) {
+      let row_addr = addr.wrapping_add(row);
+      let bytes: Vec<u8> = (0..8.min(len - row)).map(|i| bus.read8(row_addr.wrapping_add(i))).collect();
+      let hex: String = bytes.iter().map(|b| format!("{:02X} ", b)).collect();
+      let ascii: String = bytes.iter().map(|&b| if (0x20..0x7f).contains(&b) { b as char } else { '.' }).collect();
+      println!("{:04X}  {:<24}{}", row_addr, hex, ascii);
+    }
+  }
+
+  fn disasm(&self, bus: &dyn Bus, addr: u16, count: usize) {
+    let mut pc = addr;
+    for _ in 0..count {
+      let (text, len) = disassemble(pc, bus);
+      println!("{:04X}  {}", pc, text);
+      pc = pc.wrapping_add(len as u16);
+    }
+  }
+
+  /// Returns the return address of the `JSR` at `cpu.pc()`, or `None` if
+  /// the current instruction isn't a `JSR` (in which case `next` just
+  /// single-steps like `step`).
+  fn jsr_return_address(cpu: &Cpu, bus: &dyn Bus) -> Option<u16> {
+    const JSR: u8 = 0x20;
+    (bus.read8(cpu.pc()) == JSR).then(|| cpu.pc().wrapping_add(3))
+  }
+
+  fn print_help(&self) {
+    println!("break/b <addr>, clear <addr>, step/s [n], next/n [n], continue/c,");
+    println!("reg/r [name val], mem/m <addr> [len], write/w <addr> <val>,");
+    println!("disasm/d <addr> [count], trace/t, help/h. Empty line repeats the last command.");
+  }
+}
+
+fn parse_addr(s: &str) -> Option<u16> {
+  let s = s.trim_start_matches('$');
+  u16::from_str_radix(s, 16).ok().or_else(|| s.parse().ok())
+}
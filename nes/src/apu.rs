@@ -0,0 +1,600 @@
+
+// NES APU (2A03) emulation: two pulse channels, triangle, noise and DMC,
+// clocked from the CPU cycle count and mixed down to a float sample stream.
+
+const CPU_CLOCK_HZ: f64 = 1_789_773.0;
+const SAMPLE_RATE_HZ: f64 = 44_100.0;
+
+const LENGTH_TABLE: [u8; 32] = [
+  10, 254, 20, 2, 40, 4, 80, 6, 160, 8, 60, 10, 14, 12, 26, 14,
+  12, 16, 24, 18, 48, 20, 96, 22, 192, 24, 72, 26, 16, 28, 32, 30,
+];
+
+const DUTY_TABLE: [[u8; 8]; 4] = [
+  [0, 1, 0, 0, 0, 0, 0, 0],
+  [0, 1, 1, 0, 0, 0, 0, 0],
+  [0, 1, 1, 1, 1, 0, 0, 0],
+  [1, 0, 0, 1, 1, 1, 1, 1],
+];
+
+const TRIANGLE_TABLE: [u8; 32] = [
+  15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0,
+  0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15,
+];
+
+const NOISE_PERIOD_TABLE: [u16; 16] = [
+  4, 8, 16, 32, 64, 96, 128, 160, 202, 254, 380, 508, 762, 1016, 2034, 4068,
+];
+
+#[derive(Default)]
+struct Envelope {
+  start: bool,
+  decay: u8,
+  divider: u8,
+  loop_flag: bool,
+  constant: bool,
+  volume: u8,
+}
+
+impl Envelope {
+  fn clock(&mut self) {
+    if self.start {
+      self.start = false;
+      self.decay = 15;
+      self.divider = self.volume;
+    } else if self.divider == 0 {
+      self.divider = self.volume;
+      if self.decay > 0 {
+        self.decay -= 1;
+      } else if self.loop_flag {
+        self.decay = 15;
+      }
+    } else {
+      self.divider -= 1;
+    }
+  }
+
+  fn output(&self) -> u8 {
+    if self.constant { self.volume } else { self.decay }
+  }
+}
+
+#[derive(Default)]
+struct Sweep {
+  enabled: bool,
+  period: u8,
+  negate: bool,
+  shift: u8,
+  divider: u8,
+  reload: bool,
+}
+
+#[derive(Default)]
+struct Pulse {
+  enabled: bool,
+  duty: u8,
+  duty_pos: u8,
+  timer: u16,
+  timer_period: u16,
+  length_counter: u8,
+  length_halt: bool,
+  envelope: Envelope,
+  sweep: Sweep,
+  negate_from_one: bool, // pulse 1 sweeps with one's complement, pulse 2 with two's complement
+}
+
+impl Pulse {
+  fn clock_timer(&mut self) {
+    if self.timer == 0 {
+      self.timer = self.timer_period;
+      self.duty_pos = (self.duty_pos + 1) % 8;
+    } else {
+      self.timer -= 1;
+    }
+  }
+
+  fn target_period(&self) -> i32 {
+    let change = (self.timer_period >> self.sweep.shift) as i32;
+    if self.sweep.negate {
+      let delta = if self.negate_from_one { -change - 1 } else { -change };
+      self.timer_period as i32 + delta
+    } else {
+      self.timer_period as i32 + change
+    }
+  }
+
+  fn clock_sweep(&mut self) {
+    if self.sweep.divider == 0 && self.sweep.enabled && self.sweep.shift > 0 {
+      let target = self.target_period();
+      if target >= 0 && target <= 0x7FF {
+        self.timer_period = target as u16;
+      }
+    }
+    if self.sweep.divider == 0 || self.sweep.reload {
+      self.sweep.divider = self.sweep.period;
+      self.sweep.reload = false;
+    } else {
+      self.sweep.divider -= 1;
+    }
+  }
+
+  fn clock_length(&mut self) {
+    if !self.length_halt && self.length_counter > 0 {
+      self.length_counter -= 1;
+    }
+  }
+
+  fn muted(&self) -> bool {
+    self.timer_period < 8 || self.target_period() > 0x7FF
+  }
+
+  fn output(&self) -> u8 {
+    if !self.enabled || self.length_counter == 0 || self.muted() {
+      return 0;
+    }
+    if DUTY_TABLE[self.duty as usize][self.duty_pos as usize] == 0 {
+      return 0;
+    }
+    self.envelope.output()
+  }
+}
+
+#[derive(Default)]
+struct Triangle {
+  enabled: bool,
+  timer: u16,
+  timer_period: u16,
+  sequence_pos: u8,
+  length_counter: u8,
+  length_halt: bool,
+  linear_counter: u8,
+  linear_reload: u8,
+  linear_reload_flag: bool,
+}
+
+impl Triangle {
+  fn clock_timer(&mut self) {
+    if self.timer == 0 {
+      self.timer = self.timer_period;
+      if self.length_counter > 0 && self.linear_counter > 0 {
+        self.sequence_pos = (self.sequence_pos + 1) % 32;
+      }
+    } else {
+      self.timer -= 1;
+    }
+  }
+
+  fn clock_linear(&mut self) {
+    if self.linear_reload_flag {
+      self.linear_counter = self.linear_reload;
+    } else if self.linear_counter > 0 {
+      self.linear_counter -= 1;
+    }
+    if !self.length_halt {
+      self.linear_reload_flag = false;
+    }
+  }
+
+  fn clock_length(&mut self) {
+    if !self.length_halt && self.length_counter > 0 {
+      self.length_counter -= 1;
+    }
+  }
+
+  fn output(&self) -> u8 {
+    if !self.enabled || self.length_counter == 0 || self.linear_counter == 0 {
+      return 0;
+    }
+    TRIANGLE_TABLE[self.sequence_pos as usize]
+  }
+}
+
+#[derive(Default)]
+struct Noise {
+  enabled: bool,
+  mode: bool,
+  timer: u16,
+  timer_period: u16,
+  shift: u16,
+  length_counter: u8,
+  length_halt: bool,
+  envelope: Envelope,
+}
+
+impl Noise {
+  fn new() -> Self {
+    Self { shift: 1, ..Default::default() }
+  }
+
+  fn clock_timer(&mut self) {
+    if self.timer == 0 {
+      self.timer = self.timer_period;
+      let feedback_bit = if self.mode { 6 } else { 1 };
+      let feedback = (self.shift & 1) ^ ((self.shift >> feedback_bit) & 1);
+      self.shift >>= 1;
+      self.shift |= feedback << 14;
+    } else {
+      self.timer -= 1;
+    }
+  }
+
+  fn clock_length(&mut self) {
+    if !self.length_halt && self.length_counter > 0 {
+      self.length_counter -= 1;
+    }
+  }
+
+  fn output(&self) -> u8 {
+    if !self.enabled || self.length_counter == 0 || (self.shift & 1) == 1 {
+      return 0;
+    }
+    self.envelope.output()
+  }
+}
+
+#[derive(Default)]
+struct Dmc {
+  enabled: bool,
+  irq_enabled: bool,
+  irq_pending: bool,
+  loop_flag: bool,
+  rate: u16,
+  timer: u16,
+  output_level: u8,
+  sample_address: u16,
+  sample_length: u16,
+  bytes_remaining: u16,
+  current_address: u16,
+  shift_register: u8,
+  bits_remaining: u8,
+  silence: bool,
+}
+
+impl Dmc {
+  // Returns true exactly when the output unit just ran out of shifted
+  // bits and needs the next sample byte loaded via `load_byte`; the
+  // memory fetch itself happens a level up in `Apu::tick`, since the DMC
+  // has no bus access of its own.
+  fn clock_timer(&mut self) -> bool {
+    if self.timer == 0 {
+      self.timer = self.rate;
+      if self.bits_remaining == 0 {
+        self.bits_remaining = 8;
+      }
+      if !self.silence {
+        if self.shift_register & 1 == 1 {
+          if self.output_level <= 125 { self.output_level += 2; }
+        } else if self.output_level >= 2 {
+          self.output_level -= 2;
+        }
+      }
+      self.shift_register >>= 1;
+      self.bits_remaining -= 1;
+      return self.bits_remaining == 0 && self.enabled && self.bytes_remaining > 0;
+    }
+    self.timer -= 1;
+    false
+  }
+
+  // Loads one freshly-fetched sample byte into the shift register,
+  // advances `current_address` (wrapping $8000-$FFFF like the real DMC
+  // unit), and either restarts the sample (loop flag) or flags an IRQ
+  // once `bytes_remaining` hits zero.
+  fn load_byte(&mut self, byte: u8) {
+    self.shift_register = byte;
+    self.silence = false;
+    self.current_address = if self.current_address == 0xFFFF { 0x8000 } else { self.current_address + 1 };
+    self.bytes_remaining -= 1;
+
+    if self.bytes_remaining == 0 {
+      if self.loop_flag {
+        self.current_address = self.sample_address;
+        self.bytes_remaining = self.sample_length;
+      } else {
+        // No more bytes to load and nothing left looping back: go quiet
+        // instead of leaving the output unit shifting the last loaded byte
+        // forever.
+        self.silence = true;
+        if self.irq_enabled {
+          self.irq_pending = true;
+        }
+      }
+    }
+  }
+
+  fn output(&self) -> u8 {
+    self.output_level
+  }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SequencerMode {
+  FourStep,
+  FiveStep,
+}
+
+// Downsamples the ~1.789773 MHz APU clock to the host sample rate using a
+// fractional accumulator, buffering finished samples until the frontend
+// drains them once per frame.
+pub struct Apu {
+  pulse1: Pulse,
+  pulse2: Pulse,
+  triangle: Triangle,
+  noise: Noise,
+  dmc: Dmc,
+
+  mode: SequencerMode,
+  irq_inhibit: bool,
+  frame_irq: bool,
+  sequencer_cycle: usize,
+
+  cycles_since_sample: f64,
+  samples: Vec<f32>,
+}
+
+impl Apu {
+  pub fn new() -> Self {
+    Self {
+      pulse1: Pulse { negate_from_one: true, ..Default::default() },
+      pulse2: Pulse::default(),
+      triangle: Triangle::default(),
+      noise: Noise::new(),
+      dmc: Dmc::default(),
+      mode: SequencerMode::FourStep,
+      irq_inhibit: false,
+      frame_irq: false,
+      sequencer_cycle: 0,
+      cycles_since_sample: 0.0,
+      samples: Vec::new(),
+    }
+  }
+
+  pub fn write_register(&mut self, address: u16, value: u8) {
+    match address {
+      0x4000 => {
+        self.pulse1.duty = value >> 6;
+        self.pulse1.length_halt = value & 0x20 != 0;
+        self.pulse1.envelope.loop_flag = value & 0x20 != 0;
+        self.pulse1.envelope.constant = value & 0x10 != 0;
+        self.pulse1.envelope.volume = value & 0x0F;
+      }
+      0x4001 => {
+        self.pulse1.sweep.enabled = value & 0x80 != 0;
+        self.pulse1.sweep.period = (value >> 4) & 0x07;
+        self.pulse1.sweep.negate = value & 0x08 != 0;
+        self.pulse1.sweep.shift = value & 0x07;
+        self.pulse1.sweep.reload = true;
+      }
+      0x4002 => self.pulse1.timer_period = (self.pulse1.timer_period & 0x700) | value as u16,
+      0x4003 => {
+        self.pulse1.timer_period = (self.pulse1.timer_period & 0xFF) | ((value as u16 & 0x07) << 8);
+        if self.pulse1.enabled {
+          self.pulse1.length_counter = LENGTH_TABLE[(value >> 3) as usize];
+        }
+        self.pulse1.envelope.start = true;
+        self.pulse1.duty_pos = 0;
+      }
+
+      0x4004 => {
+        self.pulse2.duty = value >> 6;
+        self.pulse2.length_halt = value & 0x20 != 0;
+        self.pulse2.envelope.loop_flag = value & 0x20 != 0;
+        self.pulse2.envelope.constant = value & 0x10 != 0;
+        self.pulse2.envelope.volume = value & 0x0F;
+      }
+      0x4005 => {
+        self.pulse2.sweep.enabled = value & 0x80 != 0;
+        self.pulse2.sweep.period = (value >> 4) & 0x07;
+        self.pulse2.sweep.negate = value & 0x08 != 0;
+        self.pulse2.sweep.shift = value & 0x07;
+        self.pulse2.sweep.reload = true;
+      }
+      0x4006 => self.pulse2.timer_period = (self.pulse2.timer_period & 0x700) | value as u16,
+      0x4007 => {
+        self.pulse2.timer_period = (self.pulse2.timer_period & 0xFF) | ((value as u16 & 0x07) << 8);
+        if self.pulse2.enabled {
+          self.pulse2.length_counter = LENGTH_TABLE[(value >> 3) as usize];
+        }
+        self.pulse2.envelope.start = true;
+        self.pulse2.duty_pos = 0;
+      }
+
+      0x4008 => {
+        self.triangle.length_halt = value & 0x80 != 0;
+        self.triangle.linear_reload = value & 0x7F;
+      }
+      0x400A => self.triangle.timer_period = (self.triangle.timer_period & 0x700) | value as u16,
+      0x400B => {
+        self.triangle.timer_period = (self.triangle.timer_period & 0xFF) | ((value as u16 & 0x07) << 8);
+        if self.triangle.enabled {
+          self.triangle.length_counter = LENGTH_TABLE[(value >> 3) as usize];
+        }
+        self.triangle.linear_reload_flag = true;
+      }
+
+      0x400C => {
+        self.noise.length_halt = value & 0x20 != 0;
+        self.noise.envelope.loop_flag = value & 0x20 != 0;
+        self.noise.envelope.constant = value & 0x10 != 0;
+        self.noise.envelope.volume = value & 0x0F;
+      }
+      0x400E => {
+        self.noise.mode = value & 0x80 != 0;
+        self.noise.timer_period = NOISE_PERIOD_TABLE[(value & 0x0F) as usize];
+      }
+      0x400F => {
+        if self.noise.enabled {
+          self.noise.length_counter = LENGTH_TABLE[(value >> 3) as usize];
+        }
+        self.noise.envelope.start = true;
+      }
+
+      0x4010 => {
+        self.dmc.irq_enabled = value & 0x80 != 0;
+        self.dmc.loop_flag = value & 0x40 != 0;
+        self.dmc.rate = DMC_RATE_TABLE[(value & 0x0F) as usize];
+      }
+      0x4011 => self.dmc.output_level = value & 0x7F,
+      0x4012 => self.dmc.sample_address = 0xC000 + (value as u16 * 64),
+      0x4013 => self.dmc.sample_length = (value as u16 * 16) + 1,
+
+      0x4015 => {
+        self.pulse1.enabled = value & 0x01 != 0;
+        self.pulse2.enabled = value & 0x02 != 0;
+        self.triangle.enabled = value & 0x04 != 0;
+        self.noise.enabled = value & 0x08 != 0;
+        self.dmc.enabled = value & 0x10 != 0;
+        self.dmc.irq_pending = false;
+        if !self.pulse1.enabled { self.pulse1.length_counter = 0; }
+        if !self.pulse2.enabled { self.pulse2.length_counter = 0; }
+        if !self.triangle.enabled { self.triangle.length_counter = 0; }
+        if !self.noise.enabled { self.noise.length_counter = 0; }
+        if self.dmc.enabled {
+          if self.dmc.bytes_remaining == 0 {
+            self.dmc.current_address = self.dmc.sample_address;
+            self.dmc.bytes_remaining = self.dmc.sample_length;
+          }
+        } else {
+          self.dmc.bytes_remaining = 0;
+        }
+      }
+      0x4017 => {
+        self.mode = if value & 0x80 != 0 { SequencerMode::FiveStep } else { SequencerMode::FourStep };
+        self.irq_inhibit = value & 0x40 != 0;
+        if self.irq_inhibit {
+          self.frame_irq = false;
+        }
+        self.sequencer_cycle = 0;
+        if self.mode == SequencerMode::FiveStep {
+          self.clock_quarter_frame();
+          self.clock_half_frame();
+        }
+      }
+      _ => {}
+    }
+  }
+
+  pub fn read_status(&mut self) -> u8 {
+    let mut status = 0;
+    if self.pulse1.length_counter > 0 { status |= 0x01; }
+    if self.pulse2.length_counter > 0 { status |= 0x02; }
+    if self.triangle.length_counter > 0 { status |= 0x04; }
+    if self.noise.length_counter > 0 { status |= 0x08; }
+    if self.dmc.bytes_remaining > 0 { status |= 0x10; }
+    if self.frame_irq { status |= 0x40; }
+    if self.dmc.irq_pending { status |= 0x80; }
+    self.frame_irq = false;
+    status
+  }
+
+  fn clock_quarter_frame(&mut self) {
+    self.pulse1.envelope.clock();
+    self.pulse2.envelope.clock();
+    self.noise.envelope.clock();
+    self.triangle.clock_linear();
+  }
+
+  fn clock_half_frame(&mut self) {
+    self.pulse1.clock_length();
+    self.pulse2.clock_length();
+    self.triangle.clock_length();
+    self.noise.clock_length();
+    self.pulse1.clock_sweep();
+    self.pulse2.clock_sweep();
+  }
+
+  // Frame sequencer runs at ~240Hz, i.e. every ~7457.5 CPU cycles in 4-step
+  // mode; steps 1 and 3 clock envelopes/linear counter only, step 2 and 4
+  // (or 5) additionally clock length counters and sweep units.
+  fn clock_frame_sequencer(&mut self) {
+    self.sequencer_cycle += 1;
+    match self.mode {
+      SequencerMode::FourStep => match self.sequencer_cycle {
+        7457 => self.clock_quarter_frame(),
+        14913 => { self.clock_quarter_frame(); self.clock_half_frame(); }
+        22371 => self.clock_quarter_frame(),
+        29829 => {
+          self.clock_quarter_frame();
+          self.clock_half_frame();
+          if !self.irq_inhibit { self.frame_irq = true; }
+          self.sequencer_cycle = 0;
+        }
+        _ => {}
+      },
+      SequencerMode::FiveStep => match self.sequencer_cycle {
+        7457 => self.clock_quarter_frame(),
+        14913 => { self.clock_quarter_frame(); self.clock_half_frame(); }
+        22371 => self.clock_quarter_frame(),
+        37281 => {
+          self.clock_quarter_frame();
+          self.clock_half_frame();
+          self.sequencer_cycle = 0;
+        }
+        _ => {}
+      },
+    }
+  }
+
+  fn mix(&self) -> f32 {
+    let p1 = self.pulse1.output() as f32;
+    let p2 = self.pulse2.output() as f32;
+    let t = self.triangle.output() as f32;
+    let n = self.noise.output() as f32;
+    let d = self.dmc.output() as f32;
+
+    let pulse_out = if p1 + p2 == 0.0 { 0.0 } else { 95.88 / (8128.0 / (p1 + p2) + 100.0) };
+    let tnd_denom = t / 8227.0 + n / 12241.0 + d / 22638.0;
+    let tnd_out = if tnd_denom == 0.0 { 0.0 } else { 159.79 / (1.0 / tnd_denom + 100.0) };
+
+    pulse_out + tnd_out
+  }
+
+  /// Clocks the APU `cpu_cycles` CPU cycles forward, buffering finished
+  /// output samples at the host sample rate. `read_mem` fetches a single
+  /// byte from CPU address space, for the DMC channel to pull its next
+  /// sample byte from whatever the cartridge has mapped at `$C000-$FFFF`.
+  pub fn tick(&mut self, cpu_cycles: usize, mut read_mem: impl FnMut(u16) -> u8) {
+    for _ in 0..cpu_cycles {
+      self.clock_frame_sequencer();
+
+      self.triangle.clock_timer();
+      // Pulse and noise timers are clocked from the APU's half-rate clock,
+      // i.e. every other CPU cycle. The DMC timer isn't: DMC_RATE_TABLE
+      // already holds CPU-cycle periods, so it's clocked every cycle like
+      // the frame sequencer above, not gated behind sequencer_cycle too.
+      if self.sequencer_cycle % 2 == 0 {
+        self.pulse1.clock_timer();
+        self.pulse2.clock_timer();
+        self.noise.clock_timer();
+      }
+      if self.dmc.clock_timer() {
+        let byte = read_mem(self.dmc.current_address);
+        self.dmc.load_byte(byte);
+      }
+
+      self.cycles_since_sample += 1.0;
+      let cycles_per_sample = CPU_CLOCK_HZ / SAMPLE_RATE_HZ;
+      if self.cycles_since_sample >= cycles_per_sample {
+        self.cycles_since_sample -= cycles_per_sample;
+        self.samples.push(self.mix());
+      }
+    }
+  }
+
+  /// Drains and returns all samples buffered since the last call, ready to
+  /// be forwarded to `HostSystem::audio`.
+  pub fn drain_samples(&mut self) -> Vec<f32> {
+    std::mem::take(&mut self.samples)
+  }
+
+  /// Returns and clears the DMC's pending IRQ flag (raised when a
+  /// non-looping sample runs out of bytes with IRQs enabled). The frame
+  /// sequencer's IRQ is consumed separately by `read_status`.
+  pub fn dmc_irq_pending(&mut self) -> bool {
+    std::mem::take(&mut self.dmc.irq_pending)
+  }
+}
+
+const DMC_RATE_TABLE: [u16; 16] = [
+  428, 380, 340, 320, 286, 254, 226, 214, 190, 160, 142, 128, 106, 84, 72, 54,
+];
@@ -0,0 +1,94 @@
+
+// Parses an iNES (.nes) ROM image into its header fields and raw PRG/CHR
+// banks, without committing to any particular mapper - that's `mappers`'s
+// job, driven off `Cartridge::mapper_number`.
+
+const INES_MAGIC: [u8; 4] = [0x4E, 0x45, 0x53, 0x1A]; // "NES\x1A"
+const PRG_BANK_SIZE: usize = 16 * 1024;
+const CHR_BANK_SIZE: usize = 8 * 1024;
+const HEADER_SIZE: usize = 16;
+const TRAINER_SIZE: usize = 512;
+
+#[derive(Debug)]
+pub enum CartridgeError {
+  BadMagic,
+  Truncated,
+}
+
+impl std::fmt::Display for CartridgeError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      CartridgeError::BadMagic => write!(f, "not an iNES ROM"),
+      CartridgeError::Truncated => write!(f, "ROM file is truncated"),
+    }
+  }
+}
+
+impl std::error::Error for CartridgeError {}
+
+#[derive(Clone)]
+pub struct Cartridge {
+  mapper_number: u8,
+  four_screen_mirroring: bool,
+  vertical_mirroring: bool,
+  prg_rom: Vec<u8>,
+  chr_rom: Vec<u8>,
+}
+
+impl Cartridge {
+  pub fn blow_on_cartridge(rom: &[u8]) -> Result<Self, CartridgeError> {
+    if rom.len() < HEADER_SIZE {
+      return Err(CartridgeError::Truncated);
+    }
+    if rom[0..4] != INES_MAGIC {
+      return Err(CartridgeError::BadMagic);
+    }
+
+    let prg_banks = rom[4] as usize;
+    let chr_banks = rom[5] as usize;
+    let flags6 = rom[6];
+    let flags7 = rom[7];
+
+    let has_trainer = flags6 & 0x04 != 0;
+    let vertical_mirroring = flags6 & 0x01 != 0;
+    let four_screen_mirroring = flags6 & 0x08 != 0;
+    let mapper_number = (flags6 >> 4) | (flags7 & 0xF0);
+
+    let mut offset = HEADER_SIZE;
+    if has_trainer {
+      offset += TRAINER_SIZE;
+    }
+
+    let prg_size = prg_banks * PRG_BANK_SIZE;
+    let chr_size = chr_banks * CHR_BANK_SIZE;
+    if rom.len() < offset + prg_size + chr_size {
+      return Err(CartridgeError::Truncated);
+    }
+
+    let prg_rom = rom[offset..offset + prg_size].to_vec();
+    offset += prg_size;
+    let chr_rom = rom[offset..offset + chr_size].to_vec();
+
+    Ok(Self { mapper_number, four_screen_mirroring, vertical_mirroring, prg_rom, chr_rom })
+  }
+
+  pub fn mapper_number(&self) -> u8 {
+    self.mapper_number
+  }
+
+  pub fn vertical_mirroring(&self) -> bool {
+    self.vertical_mirroring
+  }
+
+  pub fn four_screen_mirroring(&self) -> bool {
+    self.four_screen_mirroring
+  }
+
+  pub fn prg_rom(&self) -> &[u8] {
+    &self.prg_rom
+  }
+
+  pub fn chr_rom(&self) -> &[u8] {
+    &self.chr_rom
+  }
+}
@@ -0,0 +1,84 @@
+
+// `embedded-graphics` integration: lets the emulator drive no_std displays
+// (SSD1306, ILI9341, etc.) through the same `HostSystem` trait SDL uses,
+// instead of requiring a full desktop windowing stack.
+
+use embedded_graphics::pixelcolor::Rgb888;
+use embedded_graphics::prelude::*;
+use embedded_graphics::Pixel;
+
+use crate::frame::RenderFrame;
+use crate::joypad::Joypad;
+use crate::nes::{HostSystem, Shutdown};
+
+impl OriginDimensions for RenderFrame {
+  fn size(&self) -> Size {
+    Size::new(RenderFrame::WIDTH as u32, RenderFrame::HEIGHT as u32)
+  }
+}
+
+/// Iterates a `RenderFrame` as embedded-graphics `Rgb888` pixels, so it can
+/// be drawn with `DrawTarget::draw_iter`.
+pub struct FramePixels<'a> {
+  frame: &'a RenderFrame,
+  next: usize,
+}
+
+impl<'a> FramePixels<'a> {
+  pub fn new(frame: &'a RenderFrame) -> Self {
+    Self { frame, next: 0 }
+  }
+}
+
+impl<'a> Iterator for FramePixels<'a> {
+  type Item = Pixel<Rgb888>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.next >= RenderFrame::WIDTH * RenderFrame::HEIGHT {
+      return None;
+    }
+
+    let x = self.next % RenderFrame::WIDTH;
+    let y = self.next / RenderFrame::WIDTH;
+    let (r, g, b) = self.frame.pixel_rgb(x, y);
+    self.next += 1;
+
+    Some(Pixel(Point::new(x as i32, y as i32), Rgb888::new(r, g, b)))
+  }
+}
+
+/// A `HostSystem` that blits the 256x240 NES frame into any
+/// `DrawTarget<Color = Rgb888>`, e.g. an SSD1306 or ILI9341 driver, and
+/// maps a caller-supplied button-state closure into the `Joypad`.
+pub struct EgHost<D, F>
+where
+  D: DrawTarget<Color = Rgb888>,
+  F: FnMut(&mut Joypad) -> bool,
+{
+  display: D,
+  poll_buttons: F,
+}
+
+impl<D, F> EgHost<D, F>
+where
+  D: DrawTarget<Color = Rgb888>,
+  F: FnMut(&mut Joypad) -> bool,
+{
+  pub fn new(display: D, poll_buttons: F) -> Self {
+    Self { display, poll_buttons }
+  }
+}
+
+impl<D, F> HostSystem for EgHost<D, F>
+where
+  D: DrawTarget<Color = Rgb888>,
+  F: FnMut(&mut Joypad) -> bool,
+{
+  fn render(&mut self, frame: &RenderFrame) {
+    let _ = self.display.draw_iter(FramePixels::new(frame));
+  }
+
+  fn poll_events(&mut self, joypad: &mut Joypad) -> Shutdown {
+    Shutdown::from((self.poll_buttons)(joypad))
+  }
+}
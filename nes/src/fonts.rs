@@ -0,0 +1,56 @@
+
+// A tiny embedded 3x5 bitmap font, just enough to draw the SHOW_FPS overlay
+// directly into a `RenderFrame` without pulling in a font-rendering crate.
+
+use crate::frame::RenderFrame;
+
+const GLYPH_WIDTH: usize = 3;
+const GLYPH_HEIGHT: usize = 5;
+const SCALE: usize = 2;
+
+// One bit per pixel, row-major, MSB first; only the digits the FPS counter
+// can actually print are defined, any other character draws as blank.
+fn glyph(c: char) -> [u8; GLYPH_HEIGHT] {
+  match c {
+    '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+    '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+    '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+    '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+    '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+    '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+    '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+    '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+    '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+    '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+    _ => [0, 0, 0, 0, 0],
+  }
+}
+
+/// Draws `text` at `(x, y)` in white, scaled up by `SCALE` so it stays
+/// legible at the NES's native 256x240 resolution.
+pub fn draw(text: &str, (x, y): (usize, usize), frame: &mut RenderFrame) {
+  for (i, c) in text.chars().enumerate() {
+    let gx = x + i * (GLYPH_WIDTH + 1) * SCALE;
+    draw_glyph(c, (gx, y), frame);
+  }
+}
+
+fn draw_glyph(c: char, (x, y): (usize, usize), frame: &mut RenderFrame) {
+  let rows = glyph(c);
+  for (row, bits) in rows.iter().enumerate() {
+    for col in 0..GLYPH_WIDTH {
+      if bits & (1 << (GLYPH_WIDTH - 1 - col)) == 0 {
+        continue;
+      }
+      for sy in 0..SCALE {
+        for sx in 0..SCALE {
+          let px = x + col * SCALE + sx;
+          let py = y + row * SCALE + sy;
+          if px < RenderFrame::WIDTH && py < RenderFrame::HEIGHT {
+            frame.set_pixel(px, py, (255, 255, 255));
+          }
+        }
+      }
+    }
+  }
+}
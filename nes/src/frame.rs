@@ -0,0 +1,39 @@
+
+// The PPU's output: one RGB24 byte triple per pixel at the NES's native
+// 256x240 resolution.
+
+#[derive(Clone)]
+pub struct RenderFrame {
+  pixels: Vec<u8>,
+}
+
+impl RenderFrame {
+  pub const WIDTH: usize = 256;
+  pub const HEIGHT: usize = 240;
+
+  pub fn new() -> Self {
+    Self { pixels: vec![0; Self::WIDTH * Self::HEIGHT * 3] }
+  }
+
+  pub fn set_pixel(&mut self, x: usize, y: usize, rgb: (u8, u8, u8)) {
+    let i = (y * Self::WIDTH + x) * 3;
+    self.pixels[i] = rgb.0;
+    self.pixels[i + 1] = rgb.1;
+    self.pixels[i + 2] = rgb.2;
+  }
+
+  pub fn pixel_rgb(&self, x: usize, y: usize) -> (u8, u8, u8) {
+    let i = (y * Self::WIDTH + x) * 3;
+    (self.pixels[i], self.pixels[i + 1], self.pixels[i + 2])
+  }
+
+  pub fn as_bytes(&self) -> &[u8] {
+    &self.pixels
+  }
+}
+
+impl Default for RenderFrame {
+  fn default() -> Self {
+    Self::new()
+  }
+}
@@ -0,0 +1,213 @@
+
+// Minimal GDB remote serial protocol (RSP) stub so an external debugger
+// (gdb, lldb, VS Code) can attach to a running ROM over TCP and drive the
+// existing CPU/Bus through the usual stop/step/continue/breakpoint dance.
+
+use std::collections::HashSet;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+
+use mos6502::cpu::Reg;
+use mos6502::mos6502::Mos6502;
+
+pub struct GdbStub {
+  listener: TcpListener,
+  stream: Option<TcpStream>,
+  breakpoints: HashSet<u16>,
+  // Set by a previous `s` request so the *next* instruction also stops,
+  // even if it isn't at a breakpoint; cleared as soon as that stop happens.
+  step_pending: bool,
+}
+
+impl GdbStub {
+  /// Opens a TCP listener on `port`. Nothing blocks until a debugger
+  /// actually attaches, so headless and embedded builds that never call
+  /// this are unaffected.
+  pub fn listen(port: u16) -> std::io::Result<Self> {
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    listener.set_nonblocking(true)?;
+    Ok(Self { listener, stream: None, breakpoints: HashSet::new(), step_pending: false })
+  }
+
+  fn accept_if_needed(&mut self) {
+    if self.stream.is_none() {
+      if let Ok((stream, _)) = self.listener.accept() {
+        stream.set_nonblocking(false).ok();
+        self.stream = Some(stream);
+      }
+    }
+  }
+
+  /// Whether `pc` should halt execution and re-enter the monitor loop -
+  /// either it's a breakpoint, or the previous request was `s`, whose one
+  /// instruction has now executed, so execution stops again before this one.
+  pub fn should_stop(&self, pc: u16) -> bool {
+    self.breakpoints.contains(&pc) || self.step_pending
+  }
+
+  /// Called from `Nes::step_instruction` right before a PC that
+  /// `should_stop` flagged would execute. Blocks on the socket, serving
+  /// `g`/`G`/`m`/`M`/`Z`/`z`/`?` requests, until the remote debugger sends
+  /// `s` (single-step, which also arms `step_pending` for the very next
+  /// instruction) or `c` (continue, which free-runs until the next
+  /// breakpoint).
+  pub fn break_and_serve(&mut self, machine: &mut Mos6502) -> StopReason {
+    self.step_pending = false;
+    self.accept_if_needed();
+    self.send_stop_reply();
+
+    loop {
+      let Some(packet) = self.read_packet() else { return StopReason::Continue };
+      match packet.as_bytes().first() {
+        Some(b'?') => self.send_stop_reply(),
+        Some(b'g') => self.send_registers(machine),
+        Some(b'G') => {
+          if self.set_registers(machine, &packet[1..]) { self.ack(); } else { self.send_empty(); }
+        }
+        Some(b'm') => self.read_memory(machine, &packet[1..]),
+        Some(b'M') => { self.write_memory(machine, &packet[1..]); self.ack(); }
+        Some(b'Z') => { self.set_breakpoint(&packet[1..]); self.ack(); }
+        Some(b'z') => { self.clear_breakpoint(&packet[1..]); self.ack(); }
+        Some(b's') => { self.step_pending = true; return StopReason::Step; }
+        Some(b'c') => return StopReason::Continue,
+        _ => self.send_empty(),
+      }
+    }
+  }
+
+  fn read_packet(&mut self) -> Option<String> {
+    let stream = self.stream.as_mut()?;
+    let mut byte = [0u8; 1];
+    loop {
+      if stream.read_exact(&mut byte).is_err() {
+        return None;
+      }
+      if byte[0] == b'$' {
+        break;
+      }
+      // Ignore stray '+'/'-' acks and anything else between packets.
+    }
+
+    let mut payload = Vec::new();
+    loop {
+      stream.read_exact(&mut byte).ok()?;
+      if byte[0] == b'#' {
+        break;
+      }
+      payload.push(byte[0]);
+    }
+    let mut checksum = [0u8; 2];
+    stream.read_exact(&mut checksum).ok()?;
+    stream.write_all(b"+").ok()?;
+
+    String::from_utf8(payload).ok()
+  }
+
+  fn send_raw(&mut self, payload: &str) {
+    if let Some(stream) = self.stream.as_mut() {
+      let checksum: u8 = payload.bytes().fold(0u8, |acc, b| acc.wrapping_add(b));
+      let _ = write!(stream, "${}#{:02x}", payload, checksum);
+    }
+  }
+
+  fn ack(&mut self) {
+    self.send_raw("OK");
+  }
+
+  fn send_empty(&mut self) {
+    self.send_raw("");
+  }
+
+  fn send_stop_reply(&mut self) {
+    self.send_raw("S05"); // SIGTRAP
+  }
+
+  // Register block ordered A, X, Y, SP, PC, P. PC is sent little-endian
+  // (as the 6502 itself is) so two hex bytes become four hex digits.
+  fn send_registers(&mut self, machine: &Mos6502) {
+    let cpu = machine.cpu();
+    let pc = cpu.pc().to_le_bytes();
+    let payload = format!(
+      "{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}",
+      cpu[Reg::AC], cpu[Reg::X], cpu[Reg::Y], cpu[Reg::SP],
+      pc[0], pc[1],
+      cpu.flags_as_byte(),
+    );
+    self.send_raw(&payload);
+  }
+
+  // Same order/width as `send_registers`: A, X, Y, SP, PC (little-endian),
+  // P, each a 2-hex-digit byte. Returns `false` (leaving `machine`
+  // untouched) if the block isn't exactly 7 well-formed bytes.
+  fn set_registers(&mut self, machine: &mut Mos6502, hex: &str) -> bool {
+    let bytes: Option<Vec<u8>> = (0..hex.len())
+      .step_by(2)
+      .map(|i| hex.get(i..i + 2).and_then(|b| u8::from_str_radix(b, 16).ok()))
+      .collect();
+    let Some(bytes) = bytes else { return false };
+    let [ac, x, y, sp, pc_lo, pc_hi, flags]: [u8; 7] = match bytes.try_into() {
+      Ok(bytes) => bytes,
+      Err(_) => return false,
+    };
+
+    let cpu = machine.cpu_mut();
+    cpu[Reg::AC] = ac;
+    cpu[Reg::X] = x;
+    cpu[Reg::Y] = y;
+    cpu[Reg::SP] = sp;
+    cpu.set_pc(u16::from_le_bytes([pc_lo, pc_hi]));
+    cpu.set_flags_as_byte(flags);
+    true
+  }
+
+  fn read_memory(&mut self, machine: &Mos6502, args: &str) {
+    let Some((addr, len)) = parse_addr_len(args) else { self.send_empty(); return };
+    let bus = machine.bus();
+    let mut payload = String::with_capacity(len as usize * 2);
+    for offset in 0..len {
+      payload.push_str(&format!("{:02x}", bus.read8(addr.wrapping_add(offset))));
+    }
+    self.send_raw(&payload);
+  }
+
+  fn write_memory(&mut self, machine: &mut Mos6502, args: &str) {
+    let Some((header, data)) = args.split_once(':') else { return };
+    let Some((addr, _len)) = parse_addr_len(header) else { return };
+    let bus = machine.bus_mut();
+    for (offset, byte) in data.as_bytes().chunks(2).enumerate() {
+      if let Ok(val) = u8::from_str_radix(std::str::from_utf8(byte).unwrap_or(""), 16) {
+        bus.write8(val, addr.wrapping_add(offset as u16));
+      }
+    }
+  }
+
+  fn set_breakpoint(&mut self, args: &str) {
+    if let Some(addr) = parse_z_packet(args) {
+      self.breakpoints.insert(addr);
+    }
+  }
+
+  fn clear_breakpoint(&mut self, args: &str) {
+    if let Some(addr) = parse_z_packet(args) {
+      self.breakpoints.remove(&addr);
+    }
+  }
+}
+
+pub enum StopReason {
+  Step,
+  Continue,
+}
+
+fn parse_addr_len(args: &str) -> Option<(u16, u16)> {
+  let (addr, len) = args.split_once(',')?;
+  Some((u16::from_str_radix(addr, 16).ok()?, u16::from_str_radix(len, 16).ok()?))
+}
+
+// "Z0,<addr>,<kind>" / "z0,<addr>,<kind>" - only software (PC) breakpoints
+// are supported, so the leading type digit is ignored.
+fn parse_z_packet(args: &str) -> Option<u16> {
+  let mut parts = args.splitn(3, ',').skip(1);
+  let addr = parts.next()?;
+  u16::from_str_radix(addr, 16).ok()
+}
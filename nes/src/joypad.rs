@@ -0,0 +1,41 @@
+
+// The standard NES controller: 8 buttons shifted out one bit per $4016 read.
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Button {
+  A, B, Select, Start, Up, Down, Left, Right,
+}
+
+#[derive(Default)]
+pub struct Joypad {
+  buttons: u8,
+  shift: u8,
+  strobe: bool,
+}
+
+impl Joypad {
+  pub fn set_pressed(&mut self, button: Button, pressed: bool) {
+    let mask = 1 << (button as u8);
+    if pressed {
+      self.buttons |= mask;
+    } else {
+      self.buttons &= !mask;
+    }
+  }
+
+  pub fn write_strobe(&mut self, val: u8) {
+    self.strobe = val & 1 != 0;
+    if self.strobe {
+      self.shift = self.buttons;
+    }
+  }
+
+  pub fn read(&mut self) -> u8 {
+    if self.strobe {
+      self.shift = self.buttons;
+    }
+    let bit = self.shift & 1;
+    self.shift >>= 1;
+    bit
+  }
+}
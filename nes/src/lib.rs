@@ -0,0 +1,21 @@
+
+#[macro_use]
+extern crate lazy_static;
+
+#[macro_use]
+mod trace;
+
+pub mod apu;
+pub mod cartridge;
+#[cfg(feature = "embedded-graphics")]
+pub mod eg_host;
+pub mod fonts;
+pub mod frame;
+pub mod gdb;
+pub mod joypad;
+pub mod mappers;
+pub mod nes;
+pub mod nesbus;
+pub mod ppu;
+pub mod snapshot;
+pub mod threading;
@@ -0,0 +1,94 @@
+
+// Cartridge mappers: swap PRG/CHR banks in and out of the CPU/PPU address
+// space under ROM control.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::cartridge::Cartridge;
+use crate::snapshot::{self, Snapshot};
+
+pub trait Mapper: Snapshot {
+  fn read_prg(&self, address: u16) -> u8;
+  fn write_prg(&mut self, address: u16, value: u8);
+  fn read_chr(&self, address: u16) -> u8;
+  fn write_chr(&mut self, address: u16, value: u8);
+  fn vertical_mirroring(&self) -> bool;
+}
+
+pub type MapperRef = Rc<RefCell<dyn Mapper>>;
+
+pub fn for_cart(cartridge: Cartridge) -> MapperRef {
+  match cartridge.mapper_number() {
+    0 => Rc::new(RefCell::new(Nrom::new(cartridge))),
+    other => {
+      // Unsupported mappers fall back to NROM's fixed banking rather than
+      // refusing to load the ROM outright.
+      eprintln!("mapper {other} not implemented, falling back to NROM banking");
+      Rc::new(RefCell::new(Nrom::new(cartridge)))
+    }
+  }
+}
+
+/// Mapper 0 (NROM): no banking at all - PRG is either 16KB mirrored twice
+/// or a fixed 32KB, CHR is a fixed 8KB (or CHR-RAM if the cartridge has
+/// none), and there's no PRG-RAM to persist.
+struct Nrom {
+  prg_rom: Vec<u8>,
+  chr: Vec<u8>,
+  vertical_mirroring: bool,
+}
+
+impl Nrom {
+  fn new(cartridge: Cartridge) -> Self {
+    let chr = if cartridge.chr_rom().is_empty() {
+      vec![0; 8 * 1024] // CHR-RAM
+    } else {
+      cartridge.chr_rom().to_vec()
+    };
+    Self {
+      prg_rom: cartridge.prg_rom().to_vec(),
+      chr,
+      vertical_mirroring: cartridge.vertical_mirroring(),
+    }
+  }
+}
+
+impl Mapper for Nrom {
+  fn read_prg(&self, address: u16) -> u8 {
+    let offset = (address - 0x8000) as usize % self.prg_rom.len();
+    self.prg_rom[offset]
+  }
+
+  fn write_prg(&mut self, _address: u16, _value: u8) {
+    // NROM has no banking registers to write to.
+  }
+
+  fn read_chr(&self, address: u16) -> u8 {
+    self.chr[address as usize % self.chr.len()]
+  }
+
+  fn write_chr(&mut self, address: u16, value: u8) {
+    let len = self.chr.len();
+    self.chr[address as usize % len] = value;
+  }
+
+  fn vertical_mirroring(&self) -> bool {
+    self.vertical_mirroring
+  }
+}
+
+impl Snapshot for Nrom {
+  // NROM has no banking registers and no PRG-RAM, so there's nothing
+  // mapper-specific to persist beyond the CHR-RAM a cart without CHR-ROM
+  // is using as a canvas.
+  fn save(&self, out: &mut Vec<u8>) {
+    snapshot::push_bytes(out, &self.chr);
+  }
+
+  fn restore<'a>(&mut self, data: &'a [u8]) -> Result<&'a [u8], snapshot::SnapshotError> {
+    let (chr, data) = snapshot::take_exact_bytes(data, self.chr.len())?;
+    self.chr.copy_from_slice(chr);
+    Ok(data)
+  }
+}
@@ -1,9 +1,7 @@
 
 use std::{rc::Rc, cell::RefCell, time::{Instant, Duration}};
 use mos6502::{mos6502::Mos6502, memory::Bus, cpu::{Cpu, Reg}, debugger::Debugger};
-use crate::{cartridge::Cartridge, nesbus::NesBus, ppu::{ppu::{Ppu, TickEvent}}, joypad::Joypad, frame::RenderFrame, fonts, trace};
-
-const DEFAULT_FPS_MAX: usize = 60;
+use crate::{apu::Apu, cartridge::Cartridge, nesbus::{NesBus, WorkRam}, ppu::{ppu::{Ppu, TickEvent}}, joypad::Joypad, frame::RenderFrame, fonts, trace::Tag, snapshot::{self, Snapshot, SnapshotError}, gdb::GdbStub};
 
 lazy_static! {
   static ref TIME: Instant = Instant::now();
@@ -26,10 +24,16 @@ pub trait HostSystem {
     TIME.elapsed().as_millis() as usize
   }
   fn delay(&self, d: Duration) {
-    // TODO: This should not be a sleep! We still need to poll events, etc. 
+    // TODO: This should not be a sleep! We still need to poll events, etc.
     // No need to suspend EVERYTHING. SDL_Delay?
     std::thread::sleep(d);
   }
+  // Called once per frame with the audio samples mixed since the last call,
+  // as 0.0-1.0 floats at the host sample rate. Default is a no-op so hosts
+  // that don't care about sound aren't forced to implement it.
+  fn audio(&mut self, samples: &[f32]) {
+    let _ = samples;
+  }
 }
 
 #[derive(Default)]
@@ -44,10 +48,14 @@ impl HostSystem for HeadlessHost {
 pub struct Nes {
   machine: Mos6502,
   ppu: Rc<RefCell<Ppu>>,
+  apu: Rc<RefCell<Apu>>,
+  ram: Rc<RefCell<WorkRam>>,
+  mapper: crate::mappers::MapperRef,
   host: Box<dyn HostSystem>,
   joypad: Rc<RefCell<Joypad>>,
   timing: FrameTiming,
-  shutdown: Shutdown
+  shutdown: Shutdown,
+  gdb: Option<GdbStub>,
 }
 
 impl Nes {
@@ -55,8 +63,10 @@ impl Nes {
     let rom_mapper = crate::mappers::for_cart(cartridge);
 
     let ppu = Rc::new(RefCell::new(Ppu::new(rom_mapper.clone())));
+    let apu = Rc::new(RefCell::new(Apu::new()));
     let joypad = Rc::new(RefCell::new(Joypad::default()));
-    let bus = NesBus::new(rom_mapper.clone(), ppu.clone(), joypad.clone());
+    let ram = Rc::new(RefCell::new(WorkRam::default()));
+    let bus = NesBus::new(rom_mapper.clone(), ppu.clone(), apu.clone(), joypad.clone(), ram.clone());
 
     let mut cpu = Cpu::new(bus);
     cpu.reset();
@@ -64,53 +74,64 @@ impl Nes {
     let mut machine = Mos6502::new(cpu);
     machine.inc_cycles(7); // Startup cycles..
 
-    Self { 
+    Self {
       machine,
       ppu,
+      apu,
+      ram,
+      mapper: rom_mapper,
       host: Box::new(host),
       joypad,
       timing: FrameTiming::new(),
-      shutdown: Shutdown::No
+      shutdown: Shutdown::No,
+      gdb: None,
     }
   }
 
+  /// Opt-in GDB remote serial protocol stub: opens a TCP listener on
+  /// `port` so gdb/lldb/VS Code can attach and drive `Nes::tick` via
+  /// breakpoints, stepping and memory access. Headless and embedded
+  /// builds that never call this are unaffected.
+  pub fn gdb_listen(&mut self, port: u16) -> std::io::Result<()> {
+    self.gdb = Some(GdbStub::listen(port)?);
+    Ok(())
+  }
+
   pub fn insert_headless_host(cartridge: Cartridge) -> Self {
     Self::insert(cartridge, HeadlessHost::default())
   }
 
-  pub fn tick(&mut self) {
+  /// Steps exactly one CPU instruction (plus the matching PPU/APU cycles),
+  /// without touching the host at all - not even on vblank. Frontends that
+  /// want frames and input should drive `run_until_vblank` instead; this
+  /// is for fine-grained control (e.g. the nestest harness, the GDB stub).
+  pub fn step_instruction(&mut self) -> TickEvent {
     let last_pc = self.machine.cpu().pc();
 
+    if let Some(gdb) = self.gdb.as_mut() {
+      if gdb.should_stop(last_pc) {
+        gdb.break_and_serve(&mut self.machine);
+      }
+    }
+
     let cpu_cycles = self.machine.tick();
 
     let last_op = self.debugger().last_opcode();
     trace!(Tag::Cpu, "pc: ${:04x}, opcode: ${:02x}, cycles: {}", last_pc, last_op, cpu_cycles);
 
-    let mut ppu = self.ppu.borrow_mut();
-    let ppu_event = ppu.tick(cpu_cycles * 3);
-  
+    let ppu_event = self.ppu.borrow_mut().tick(cpu_cycles * 3);
+    let bus = self.machine.bus();
+    self.apu.borrow_mut().tick(cpu_cycles, |addr| bus.read8(addr));
+
     if ppu_event == TickEvent::EnteredVblank {
       trace!(Tag::PpuTiming, "==VBLANK==");
-
-      if *SHOW_FPS {
-        let fps = self.timing.fps_avg(self.host.elapsed_millis());
-        fonts::draw(fps.to_string().as_str(), (10, 10), ppu.frame_mut());
-      }
-      
-      self.host.render(ppu.frame());
-      self.shutdown = self.host.poll_events(&mut self.joypad.borrow_mut());
-      if let Some(delay)= self.timing.post_render(self.host.elapsed_millis()) {
-        self.host.delay(delay);
-      }
-      self.timing.post_delay(self.host.elapsed_millis());
-
-      if ppu.nmi_on_vblank() {
+      if self.ppu.borrow().nmi_on_vblank() {
         trace!(Tag::PpuTiming, "==NMI==");
         self.machine.cpu_mut().nmi();
       }
     }
 
-    if ppu_event == TickEvent::TriggerIrq {
+    if ppu_event == TickEvent::TriggerIrq || self.apu.borrow_mut().dmc_irq_pending() {
       self.machine.cpu_mut().irq();
     }
 
@@ -118,6 +139,49 @@ impl Nes {
       self.machine.cpu_mut().reset();
       self.shutdown = Shutdown::No
     }
+
+    ppu_event
+  }
+
+  // Shared by `tick` and `run_until_vblank`: draws the FPS overlay, drains
+  // the APU's buffered samples to the host, renders and polls input. Never
+  // sleeps - frame pacing is entirely the caller's responsibility now.
+  fn handle_vblank(&mut self) -> RenderFrame {
+    if *SHOW_FPS {
+      let fps = self.timing.fps_avg(self.host.elapsed_millis());
+      fonts::draw(fps.to_string().as_str(), (10, 10), self.ppu.borrow_mut().frame_mut());
+    }
+
+    let samples = self.apu.borrow_mut().drain_samples();
+    self.host.audio(&samples);
+
+    let frame = self.ppu.borrow().frame().clone();
+    self.host.render(&frame);
+    self.shutdown = self.host.poll_events(&mut self.joypad.borrow_mut());
+    self.timing.post_delay(self.host.elapsed_millis());
+
+    frame
+  }
+
+  /// Backward-compatible single-instruction step that also drives
+  /// rendering/input on vblank, for callers that don't need the
+  /// run-to-vblank granularity.
+  pub fn tick(&mut self) {
+    if self.step_instruction() == TickEvent::EnteredVblank {
+      self.handle_vblank();
+    }
+  }
+
+  /// Ticks the CPU/PPU/APU until the next vblank and returns the finished
+  /// frame, without ever sleeping. Frontends should call this in their own
+  /// loop and pace themselves between calls (e.g. against a frame timer),
+  /// instead of relying on `HostSystem::delay`.
+  pub fn run_until_vblank(&mut self) -> RenderFrame {
+    loop {
+      if self.step_instruction() == TickEvent::EnteredVblank {
+        return self.handle_vblank();
+      }
+    }
   }
 
   pub fn debugger(&mut self) -> &mut Debugger {
@@ -136,33 +200,87 @@ impl Nes {
     self.machine.bus()
   }
 
+  pub fn bus_mut(&mut self) -> &mut Box<dyn Bus> {
+    self.machine.bus_mut()
+  }
+
   pub fn cpu_ticks(&self) -> usize {
     self.machine.ticks()
   }
-  
-  pub fn fps_max(&mut self, fps_max: usize) {
-    self.timing.fps_max(fps_max);
-  }
 
   pub fn powered_on(&self) -> bool {
     self.shutdown != Shutdown::Yes
   }
+
+  /// Resets the CPU in place, as if the console's reset button was pressed.
+  pub fn reset(&mut self) {
+    self.machine.cpu_mut().reset();
+  }
+
+  /// Swaps in a new cartridge without tearing down the host or the thread
+  /// driving this `Nes` - handy for a GUI's "open ROM" flow.
+  pub fn swap_cartridge(&mut self, cartridge: Cartridge) {
+    let rom_mapper = crate::mappers::for_cart(cartridge);
+
+    self.ppu.replace(Ppu::new(rom_mapper.clone()));
+    self.apu.replace(Apu::new());
+    self.ram.replace(WorkRam::default());
+    let bus = NesBus::new(rom_mapper.clone(), self.ppu.clone(), self.apu.clone(), self.joypad.clone(), self.ram.clone());
+
+    let mut cpu = Cpu::new(bus);
+    cpu.reset();
+    self.machine = Mos6502::new(cpu);
+    self.machine.inc_cycles(7);
+    self.mapper = rom_mapper;
+  }
+
+  /// Serializes the entire running machine - CPU registers and cycle
+  /// count, PPU, work RAM and the active mapper's banking registers -
+  /// into a versioned blob that `load_state` can restore later. Goes
+  /// through the `Rc<RefCell<..>>` handles `Nes` already holds rather than
+  /// `self.machine.bus()`, since the CPU only exposes that as
+  /// `&Box<dyn Bus>` and the upstream `Bus` trait has no `Snapshot` bound
+  /// to call through.
+  pub fn save_state(&self) -> Vec<u8> {
+    let mut out = Vec::new();
+    snapshot::write_header(&mut out);
+    self.machine.cpu().save(&mut out);
+    snapshot::push_u64(&mut out, self.machine.cycles() as u64);
+    self.ppu.borrow().save(&mut out);
+    self.ram.borrow().save(&mut out);
+    self.mapper.borrow().save(&mut out);
+    out
+  }
+
+  /// Restores a machine previously serialized by `save_state`. Rejects
+  /// blobs that don't start with the expected magic header, and blobs
+  /// that run out of bytes partway through a component, so a save from an
+  /// incompatible version or a truncated file is never silently
+  /// half-applied.
+  pub fn load_state(&mut self, data: &[u8]) -> Result<(), SnapshotError> {
+    let data = snapshot::strip_header(data)?;
+    let data = self.machine.cpu_mut().restore(data)?;
+    let (cycles, data) = snapshot::take_u64(data)?;
+    self.machine.inc_cycles((cycles as usize).wrapping_sub(self.machine.cycles()));
+    let data = self.ppu.borrow_mut().restore(data)?;
+    let data = self.ram.borrow_mut().restore(data)?;
+    self.mapper.borrow_mut().restore(data)?;
+    Ok(())
+  }
 }
 
 
+// Frame counter used only for the SHOW_FPS overlay; actual frame pacing now
+// lives in the frontend, which drives `Nes::run_until_vblank` on its own
+// timer instead of relying on a sleep baked into the core.
 struct FrameTiming {
   frame_n: usize,
   last_frame_timestamp: usize,
-  frame_limit_ms: usize,
 }
 
 impl FrameTiming {
   pub fn new() -> Self {
-    Self { frame_n: 0, last_frame_timestamp: 0, frame_limit_ms: 1000 / DEFAULT_FPS_MAX }
-  }
-
-  pub fn fps_max(&mut self, fps_max: usize) {
-    self.frame_limit_ms = 1000 / fps_max;
+    Self { frame_n: 0, last_frame_timestamp: 0 }
   }
 
   pub fn fps_avg(&mut self, elapsed: usize) -> usize {
@@ -174,18 +292,6 @@ impl FrameTiming {
     }
   }
 
-  pub fn post_render(&mut self, elapsed: usize) -> Option<Duration> {
-    if self.last_frame_timestamp != 0 {
-      let ms_to_render_frame = elapsed - self.last_frame_timestamp;
-      // println!("took: {}ms, target: {}ms", ms_to_render_frame, self.frame_limit_ms);
-      if ms_to_render_frame < self.frame_limit_ms {
-        return Some(Duration::from_millis((self.frame_limit_ms - ms_to_render_frame) as u64));
-      }
-    }
-
-    None
-  }
-
   pub fn post_delay(&mut self, elapsed: usize) {
     self.frame_n += 1;
     self.last_frame_timestamp = elapsed;
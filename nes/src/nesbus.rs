@@ -0,0 +1,83 @@
+
+// The CPU's view of the console: 2KB of work RAM mirrored up to $1FFF, PPU
+// registers mirrored every 8 bytes across $2000-$3FFF, the APU/joypad
+// register window at $4000-$4017, and the cartridge's PRG space from
+// $8000 up, all multiplexed behind `mos6502::memory::Bus`.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use mos6502::memory::Bus;
+
+use crate::apu::Apu;
+use crate::joypad::Joypad;
+use crate::mappers::MapperRef;
+use crate::ppu::ppu::Ppu;
+use crate::snapshot::{self, Snapshot};
+
+const WORK_RAM_SIZE: usize = 2 * 1024;
+
+/// The console's 2KB of work RAM, held in its own `Rc<RefCell<..>>` (same
+/// pattern as the PPU/APU/joypad) so `Nes::save_state` can snapshot it
+/// directly instead of going through `Bus`, which has no `Snapshot` bound.
+#[derive(Default)]
+pub struct WorkRam([u8; WORK_RAM_SIZE]);
+
+impl Snapshot for WorkRam {
+  fn save(&self, out: &mut Vec<u8>) {
+    snapshot::push_bytes(out, &self.0);
+  }
+
+  fn restore<'a>(&mut self, data: &'a [u8]) -> Result<&'a [u8], snapshot::SnapshotError> {
+    let (ram, data) = snapshot::take_exact_bytes(data, self.0.len())?;
+    self.0.copy_from_slice(ram);
+    Ok(data)
+  }
+}
+
+pub struct NesBus {
+  ram: Rc<RefCell<WorkRam>>,
+  ppu: Rc<RefCell<Ppu>>,
+  apu: Rc<RefCell<Apu>>,
+  mapper: MapperRef,
+  joypad: Rc<RefCell<Joypad>>,
+}
+
+impl NesBus {
+  pub fn new(mapper: MapperRef, ppu: Rc<RefCell<Ppu>>, apu: Rc<RefCell<Apu>>, joypad: Rc<RefCell<Joypad>>, ram: Rc<RefCell<WorkRam>>) -> Self {
+    Self { ram, ppu, apu, mapper, joypad }
+  }
+}
+
+impl Bus for NesBus {
+  fn read8(&self, address: u16) -> u8 {
+    match address {
+      0x0000..=0x1FFF => self.ram.borrow().0[(address & 0x07FF) as usize],
+      0x2000..=0x3FFF => self.ppu.borrow_mut().read_register(address),
+      0x4015 => self.apu.borrow_mut().read_status(),
+      0x4016 => self.joypad.borrow_mut().read(),
+      0x4017 => 0,
+      0x8000..=0xFFFF => self.mapper.borrow().read_prg(address),
+      _ => 0,
+    }
+  }
+
+  fn write8(&mut self, value: u8, address: u16) {
+    match address {
+      0x0000..=0x1FFF => self.ram.borrow_mut().0[(address & 0x07FF) as usize] = value,
+      0x2000..=0x3FFF => self.ppu.borrow_mut().write_register(address, value),
+      0x4014 => {
+        // OAM DMA: copy one 256-byte page from CPU RAM into the PPU's OAM.
+        let page = (value as u16) << 8;
+        for i in 0..=0xFFu16 {
+          let byte = self.read8(page + i);
+          self.ppu.borrow_mut().write_oam_byte(byte);
+        }
+      }
+      0x4016 => self.joypad.borrow_mut().write_strobe(value),
+      0x4000..=0x4013 | 0x4015 | 0x4017 => self.apu.borrow_mut().write_register(address, value),
+      0x8000..=0xFFFF => self.mapper.borrow_mut().write_prg(address, value),
+      _ => {}
+    }
+  }
+}
@@ -0,0 +1,381 @@
+
+// The 2C02 PPU: background rendering driven by a scanline/cycle counter
+// advanced from `tick`, with its own 2KB of nametable VRAM, a 32-byte
+// palette and 256-byte OAM living behind the usual $2000-$2007 register
+// window. OAM is stored and written (via $2004 and DMA), but sprites are
+// not drawn.
+
+use crate::frame::RenderFrame;
+use crate::mappers::MapperRef;
+use crate::snapshot::{self, Snapshot};
+
+const VRAM_SIZE: usize = 2 * 1024;
+const PALETTE_SIZE: usize = 32;
+const OAM_SIZE: usize = 256;
+
+const CYCLES_PER_SCANLINE: usize = 341;
+const SCANLINES_PER_FRAME: usize = 262;
+const VBLANK_SCANLINE: usize = 241;
+
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum TickEvent {
+  None,
+  EnteredVblank,
+  TriggerIrq,
+}
+
+#[derive(Default, Clone, Copy)]
+struct Ctrl {
+  base_nametable: u8,
+  vram_increment_32: bool,
+  sprite_pattern_table: u8,
+  background_pattern_table: u8,
+  tall_sprites: bool,
+  nmi_on_vblank: bool,
+}
+
+impl From<u8> for Ctrl {
+  fn from(v: u8) -> Self {
+    Self {
+      base_nametable: v & 0x03,
+      vram_increment_32: v & 0x04 != 0,
+      sprite_pattern_table: (v >> 3) & 1,
+      background_pattern_table: (v >> 4) & 1,
+      tall_sprites: v & 0x20 != 0,
+      nmi_on_vblank: v & 0x80 != 0,
+    }
+  }
+}
+
+#[derive(Default, Clone, Copy)]
+struct Mask {
+  show_background: bool,
+  show_sprites: bool,
+}
+
+impl From<u8> for Mask {
+  fn from(v: u8) -> Self {
+    Self { show_background: v & 0x08 != 0, show_sprites: v & 0x10 != 0 }
+  }
+}
+
+pub struct Ppu {
+  mapper: MapperRef,
+
+  vram: [u8; VRAM_SIZE],
+  palette: [u8; PALETTE_SIZE],
+  oam: [u8; OAM_SIZE],
+
+  ctrl: Ctrl,
+  mask: Mask,
+  status: u8,
+  oam_addr: u8,
+
+  // $2005/$2006 share a write-twice latch toggled by each write to either
+  // and reset by reads of $2002.
+  write_latch: bool,
+  scroll_x: u8,
+  scroll_y: u8,
+  vram_addr: u16,
+  read_buffer: u8,
+
+  scanline: usize,
+  cycle: usize,
+
+  frame: RenderFrame,
+}
+
+impl Ppu {
+  pub fn new(mapper: MapperRef) -> Self {
+    Self {
+      mapper,
+      vram: [0; VRAM_SIZE],
+      palette: [0; PALETTE_SIZE],
+      oam: [0; OAM_SIZE],
+      ctrl: Ctrl::default(),
+      mask: Mask::default(),
+      status: 0,
+      oam_addr: 0,
+      write_latch: false,
+      scroll_x: 0,
+      scroll_y: 0,
+      vram_addr: 0,
+      read_buffer: 0,
+      scanline: 0,
+      cycle: 0,
+      frame: RenderFrame::new(),
+    }
+  }
+
+  pub fn scanline(&self) -> usize {
+    self.scanline
+  }
+
+  pub fn cycle(&self) -> usize {
+    self.cycle
+  }
+
+  pub fn nmi_on_vblank(&self) -> bool {
+    self.ctrl.nmi_on_vblank
+  }
+
+  pub fn frame(&self) -> &RenderFrame {
+    &self.frame
+  }
+
+  pub fn frame_mut(&mut self) -> &mut RenderFrame {
+    &mut self.frame
+  }
+
+  fn vram_index(&self, address: u16) -> usize {
+    let address = address & 0x0FFF;
+    let table = (address / 0x0400) as usize;
+    let offset = (address % 0x0400) as usize;
+    let table = if self.mapper.borrow().vertical_mirroring() { table % 2 } else { table / 2 };
+    (table * 0x0400 + offset) % VRAM_SIZE
+  }
+
+  fn palette_index(&self, address: u16) -> usize {
+    let mut index = (address & 0x1F) as usize;
+    if index >= 16 && index % 4 == 0 {
+      index -= 16;
+    }
+    index
+  }
+
+  fn read_ppu_bus(&self, address: u16) -> u8 {
+    match address {
+      0x0000..=0x1FFF => self.mapper.borrow().read_chr(address),
+      0x2000..=0x3EFF => self.vram[self.vram_index(address)],
+      0x3F00..=0x3FFF => self.palette[self.palette_index(address)],
+      _ => 0,
+    }
+  }
+
+  fn write_ppu_bus(&mut self, address: u16, value: u8) {
+    match address {
+      0x0000..=0x1FFF => self.mapper.borrow_mut().write_chr(address, value),
+      0x2000..=0x3EFF => {
+        let i = self.vram_index(address);
+        self.vram[i] = value;
+      }
+      0x3F00..=0x3FFF => {
+        let i = self.palette_index(address);
+        self.palette[i] = value;
+      }
+      _ => {}
+    }
+  }
+
+  fn vram_increment(&self) -> u16 {
+    if self.ctrl.vram_increment_32 { 32 } else { 1 }
+  }
+
+  /// CPU-side register read at `$2000 + (address & 7)`.
+  pub fn read_register(&mut self, address: u16) -> u8 {
+    match address & 7 {
+      2 => {
+        let v = self.status;
+        self.status &= !0x80;
+        self.write_latch = false;
+        v
+      }
+      4 => self.oam[self.oam_addr as usize],
+      7 => {
+        let value = if (0x3F00..=0x3FFF).contains(&self.vram_addr) {
+          self.palette[self.palette_index(self.vram_addr)]
+        } else {
+          let buffered = self.read_buffer;
+          self.read_buffer = self.read_ppu_bus(self.vram_addr);
+          buffered
+        };
+        self.vram_addr = self.vram_addr.wrapping_add(self.vram_increment());
+        value
+      }
+      _ => 0,
+    }
+  }
+
+  /// CPU-side register write at `$2000 + (address & 7)`.
+  pub fn write_register(&mut self, address: u16, value: u8) {
+    match address & 7 {
+      0 => self.ctrl = Ctrl::from(value),
+      1 => self.mask = Mask::from(value),
+      3 => self.oam_addr = value,
+      4 => {
+        self.oam[self.oam_addr as usize] = value;
+        self.oam_addr = self.oam_addr.wrapping_add(1);
+      }
+      5 => {
+        if !self.write_latch {
+          self.scroll_x = value;
+        } else {
+          self.scroll_y = value;
+        }
+        self.write_latch = !self.write_latch;
+      }
+      6 => {
+        if !self.write_latch {
+          self.vram_addr = (self.vram_addr & 0x00FF) | ((value as u16 & 0x3F) << 8);
+        } else {
+          self.vram_addr = (self.vram_addr & 0xFF00) | value as u16;
+        }
+        self.write_latch = !self.write_latch;
+      }
+      7 => {
+        self.write_ppu_bus(self.vram_addr, value);
+        self.vram_addr = self.vram_addr.wrapping_add(self.vram_increment());
+      }
+      _ => {}
+    }
+  }
+
+  pub fn write_oam_byte(&mut self, value: u8) {
+    self.oam[self.oam_addr as usize] = value;
+    self.oam_addr = self.oam_addr.wrapping_add(1);
+  }
+
+  fn render_pixel(&mut self) {
+    let x = self.cycle - 1;
+    let y = self.scanline;
+    if x >= RenderFrame::WIDTH || y >= RenderFrame::HEIGHT {
+      return;
+    }
+    if !self.mask.show_background && !self.mask.show_sprites {
+      let backdrop = self.palette[0];
+      self.frame.set_pixel(x, y, nes_color(backdrop));
+      return;
+    }
+
+    let tile_x = (x + self.scroll_x as usize) / 8 % 32;
+    let tile_y = (y + self.scroll_y as usize) / 8 % 30;
+    let nametable_base = 0x2000 + self.ctrl.base_nametable as u16 * 0x400;
+    let tile_index = self.read_ppu_bus(nametable_base + (tile_y * 32 + tile_x) as u16);
+
+    let fine_x = (x + self.scroll_x as usize) % 8;
+    let fine_y = (y + self.scroll_y as usize) % 8;
+    let pattern_base = self.ctrl.background_pattern_table as u16 * 0x1000;
+    let plane0 = self.read_ppu_bus(pattern_base + tile_index as u16 * 16 + fine_y as u16);
+    let plane1 = self.read_ppu_bus(pattern_base + tile_index as u16 * 16 + fine_y as u16 + 8);
+    let bit = 7 - fine_x;
+    let color_index = ((plane0 >> bit) & 1) | (((plane1 >> bit) & 1) << 1);
+
+    let attr_x = tile_x / 4;
+    let attr_y = tile_y / 4;
+    let attr_byte = self.read_ppu_bus(nametable_base + 0x3C0 + (attr_y * 8 + attr_x) as u16);
+    let quadrant = ((tile_y % 4) / 2) * 2 + (tile_x % 4) / 2;
+    let palette_hi = (attr_byte >> (quadrant * 2)) & 0x03;
+
+    let palette_addr = if color_index == 0 { 0 } else { (palette_hi << 2) | color_index };
+    let color = self.palette[self.palette_index(palette_addr as u16)];
+    self.frame.set_pixel(x, y, nes_color(color));
+  }
+
+  /// Advances `ppu_cycles` PPU dots (three per CPU cycle), rendering
+  /// background pixels as the beam sweeps the visible scanlines and
+  /// flagging vblank/NMI at scanline 241.
+  pub fn tick(&mut self, ppu_cycles: usize) -> TickEvent {
+    let mut event = TickEvent::None;
+
+    for _ in 0..ppu_cycles {
+      if self.scanline < RenderFrame::HEIGHT && self.cycle >= 1 && self.cycle <= RenderFrame::WIDTH {
+        self.render_pixel();
+      }
+
+      self.cycle += 1;
+      if self.cycle >= CYCLES_PER_SCANLINE {
+        self.cycle = 0;
+        self.scanline += 1;
+
+        if self.scanline == VBLANK_SCANLINE {
+          self.status |= 0x80;
+          event = TickEvent::EnteredVblank;
+        }
+
+        if self.scanline >= SCANLINES_PER_FRAME {
+          self.scanline = 0;
+          self.status &= !0x80;
+        }
+      }
+    }
+
+    event
+  }
+}
+
+// The NES's fixed 64-color NTSC palette, as RGB24 triples.
+const PALETTE: [(u8, u8, u8); 64] = [
+  (84, 84, 84), (0, 30, 116), (8, 16, 144), (48, 0, 136),
+  (68, 0, 100), (92, 0, 48), (84, 4, 0), (60, 24, 0),
+  (32, 42, 0), (8, 58, 0), (0, 64, 0), (0, 60, 0),
+  (0, 50, 60), (0, 0, 0), (0, 0, 0), (0, 0, 0),
+  (152, 150, 152), (8, 76, 196), (48, 50, 236), (92, 30, 228),
+  (136, 20, 176), (160, 20, 100), (152, 34, 32), (120, 60, 0),
+  (84, 90, 0), (40, 114, 0), (8, 124, 0), (0, 118, 40),
+  (0, 102, 120), (0, 0, 0), (0, 0, 0), (0, 0, 0),
+  (236, 238, 236), (76, 154, 236), (120, 124, 236), (176, 98, 236),
+  (228, 84, 236), (236, 88, 180), (236, 106, 100), (212, 136, 32),
+  (160, 170, 0), (116, 196, 0), (76, 208, 32), (56, 204, 108),
+  (56, 180, 204), (60, 60, 60), (0, 0, 0), (0, 0, 0),
+  (236, 238, 236), (168, 204, 236), (188, 188, 236), (212, 178, 236),
+  (236, 174, 236), (236, 174, 212), (236, 180, 176), (228, 196, 144),
+  (204, 210, 120), (180, 222, 120), (168, 226, 144), (152, 226, 180),
+  (160, 214, 228), (160, 162, 160), (0, 0, 0), (0, 0, 0),
+];
+
+fn nes_color(index: u8) -> (u8, u8, u8) {
+  PALETTE[index as usize % 64]
+}
+
+impl Snapshot for Ppu {
+  fn save(&self, out: &mut Vec<u8>) {
+    snapshot::push_bytes(out, &self.vram);
+    snapshot::push_bytes(out, &self.palette);
+    snapshot::push_bytes(out, &self.oam);
+    let c = &self.ctrl;
+    out.push(c.base_nametable | (c.vram_increment_32 as u8) << 2 | c.sprite_pattern_table << 3
+      | c.background_pattern_table << 4 | (c.tall_sprites as u8) << 5 | (c.nmi_on_vblank as u8) << 7);
+    out.push((self.mask.show_background as u8) << 3 | (self.mask.show_sprites as u8) << 4);
+    out.push(self.status);
+    out.push(self.oam_addr);
+    out.push(self.write_latch as u8);
+    out.push(self.scroll_x);
+    out.push(self.scroll_y);
+    snapshot::push_u16(out, self.vram_addr);
+    out.push(self.read_buffer);
+    snapshot::push_u16(out, self.scanline as u16);
+    snapshot::push_u16(out, self.cycle as u16);
+  }
+
+  fn restore<'a>(&mut self, data: &'a [u8]) -> Result<&'a [u8], snapshot::SnapshotError> {
+    let (vram, data) = snapshot::take_exact_bytes(data, self.vram.len())?;
+    self.vram.copy_from_slice(vram);
+    let (palette, data) = snapshot::take_exact_bytes(data, self.palette.len())?;
+    self.palette.copy_from_slice(palette);
+    let (oam, data) = snapshot::take_exact_bytes(data, self.oam.len())?;
+    self.oam.copy_from_slice(oam);
+    let (ctrl, data) = snapshot::take_byte(data)?;
+    self.ctrl = Ctrl::from(ctrl);
+    let (mask, data) = snapshot::take_byte(data)?;
+    self.mask = Mask::from(mask);
+    let (status, data) = snapshot::take_byte(data)?;
+    self.status = status;
+    let (oam_addr, data) = snapshot::take_byte(data)?;
+    self.oam_addr = oam_addr;
+    let (write_latch, data) = snapshot::take_byte(data)?;
+    self.write_latch = write_latch != 0;
+    let (scroll_x, data) = snapshot::take_byte(data)?;
+    self.scroll_x = scroll_x;
+    let (scroll_y, data) = snapshot::take_byte(data)?;
+    self.scroll_y = scroll_y;
+    let (vram_addr, data) = snapshot::take_u16(data)?;
+    self.vram_addr = vram_addr;
+    let (read_buffer, data) = snapshot::take_byte(data)?;
+    self.read_buffer = read_buffer;
+    let (scanline, data) = snapshot::take_u16(data)?;
+    self.scanline = scanline as usize;
+    let (cycle, data) = snapshot::take_u16(data)?;
+    self.cycle = cycle as usize;
+    Ok(data)
+  }
+}
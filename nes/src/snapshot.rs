@@ -0,0 +1,146 @@
+
+// Full machine save-states: every stateful piece of the console (CPU, PPU,
+// work RAM, mapper banking registers) implements `Snapshot` so `Nes` can
+// gather and reapply it through the same `Rc<RefCell<..>>` handles it
+// already holds, without knowing the concrete type underneath.
+
+const MAGIC: &[u8; 4] = b"PTS1";
+
+/// Implemented by every piece of machine state that needs to survive a
+/// save/load cycle: the CPU, the PPU, the bus's work RAM, and each mapper.
+pub trait Snapshot {
+  /// Appends this component's state to `out`, in a format only `restore`
+  /// needs to understand.
+  fn save(&self, out: &mut Vec<u8>);
+
+  /// Reads this component's state back from the front of `data`, returning
+  /// the unconsumed remainder. Errors with `SnapshotError::Truncated`
+  /// instead of panicking if `data` runs out before this component's
+  /// state does.
+  fn restore<'a>(&mut self, data: &'a [u8]) -> Result<&'a [u8], SnapshotError>;
+}
+
+#[derive(Debug)]
+pub enum SnapshotError {
+  BadMagic,
+  Truncated,
+}
+
+impl std::fmt::Display for SnapshotError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      SnapshotError::BadMagic => write!(f, "not a potatis save state"),
+      SnapshotError::Truncated => write!(f, "save state is truncated or corrupt"),
+    }
+  }
+}
+
+impl std::error::Error for SnapshotError {}
+
+pub(crate) fn write_header(out: &mut Vec<u8>) {
+  out.extend_from_slice(MAGIC);
+}
+
+pub(crate) fn strip_header(data: &[u8]) -> Result<&[u8], SnapshotError> {
+  if data.len() < MAGIC.len() {
+    return Err(SnapshotError::Truncated);
+  }
+  let (header, rest) = data.split_at(MAGIC.len());
+  if header != MAGIC {
+    return Err(SnapshotError::BadMagic);
+  }
+  Ok(rest)
+}
+
+// Small helpers so every `Snapshot` impl serializes fixed-size integers the
+// same way instead of hand-rolling byte order at each call site. The
+// `take_*` helpers bound-check `data` and return `SnapshotError::Truncated`
+// instead of panicking, since `data` ultimately comes from a file a caller
+// could hand us half-written or from an incompatible version.
+pub(crate) fn push_u16(out: &mut Vec<u8>, val: u16) {
+  out.extend_from_slice(&val.to_le_bytes());
+}
+
+pub(crate) fn take_u16(data: &[u8]) -> Result<(u16, &[u8]), SnapshotError> {
+  if data.len() < 2 {
+    return Err(SnapshotError::Truncated);
+  }
+  let (head, rest) = data.split_at(2);
+  Ok((u16::from_le_bytes(head.try_into().unwrap()), rest))
+}
+
+pub(crate) fn push_u64(out: &mut Vec<u8>, val: u64) {
+  out.extend_from_slice(&val.to_le_bytes());
+}
+
+pub(crate) fn take_u64(data: &[u8]) -> Result<(u64, &[u8]), SnapshotError> {
+  if data.len() < 8 {
+    return Err(SnapshotError::Truncated);
+  }
+  let (head, rest) = data.split_at(8);
+  Ok((u64::from_le_bytes(head.try_into().unwrap()), rest))
+}
+
+pub(crate) fn take_byte(data: &[u8]) -> Result<(u8, &[u8]), SnapshotError> {
+  if data.is_empty() {
+    return Err(SnapshotError::Truncated);
+  }
+  let (byte, rest) = data.split_at(1);
+  Ok((byte[0], rest))
+}
+
+pub(crate) fn push_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+  push_u16(out, bytes.len() as u16);
+  out.extend_from_slice(bytes);
+}
+
+pub(crate) fn take_bytes(data: &[u8]) -> Result<(&[u8], &[u8]), SnapshotError> {
+  let (len, rest) = take_u16(data)?;
+  if rest.len() < len as usize {
+    return Err(SnapshotError::Truncated);
+  }
+  Ok(rest.split_at(len as usize))
+}
+
+/// Like `take_bytes`, but also rejects a blob whose encoded length doesn't
+/// match `len` - the size callers are about to `copy_from_slice` into -
+/// instead of letting that `copy_from_slice` panic.
+pub(crate) fn take_exact_bytes(data: &[u8], len: usize) -> Result<(&[u8], &[u8]), SnapshotError> {
+  let (bytes, rest) = take_bytes(data)?;
+  if bytes.len() != len {
+    return Err(SnapshotError::Truncated);
+  }
+  Ok((bytes, rest))
+}
+
+// `Cpu` is defined upstream in `mos6502`, but the orphan rule only requires
+// one of the trait or the type to be local, so `Snapshot` (ours) can still
+// be implemented for it here.
+impl Snapshot for mos6502::cpu::Cpu {
+  fn save(&self, out: &mut Vec<u8>) {
+    use mos6502::cpu::Reg;
+    out.push(self[Reg::AC]);
+    out.push(self[Reg::X]);
+    out.push(self[Reg::Y]);
+    out.push(self[Reg::SP]);
+    push_u16(out, self.pc());
+    out.push(self.flags_as_byte());
+  }
+
+  fn restore<'a>(&mut self, data: &'a [u8]) -> Result<&'a [u8], SnapshotError> {
+    use mos6502::cpu::Reg;
+    let (ac, data) = take_byte(data)?;
+    self[Reg::AC] = ac;
+    let (x, data) = take_byte(data)?;
+    self[Reg::X] = x;
+    let (y, data) = take_byte(data)?;
+    self[Reg::Y] = y;
+    let (sp, data) = take_byte(data)?;
+    self[Reg::SP] = sp;
+    let (pc, data) = take_u16(data)?;
+    self.set_pc(pc);
+    let (flags, data) = take_byte(data)?;
+    self.set_flags_as_byte(flags);
+    Ok(data)
+  }
+}
@@ -0,0 +1,125 @@
+
+// Lets a GUI/audio thread drive a `Nes` that lives on its own thread: the
+// emulator thread owns the `Nes` outright and polls a channel of commands
+// between frames, so a blocking sleep on either side can never stall the
+// other.
+//
+// `Nes` holds `Rc<RefCell<..>>` handles to its PPU/APU/joypad/mapper, so
+// it isn't `Send` and can never be moved into `std::thread::spawn` once
+// built. Rather than migrate that whole shared-state core to `Arc<Mutex<..>>`
+// just to satisfy the channel, the `Nes` is constructed *on* the dedicated
+// thread from its `Cartridge` and host - both of which are plain `Send`
+// data - so it never has to cross a thread boundary at all.
+
+use std::sync::mpsc::{Receiver, Sender, TryRecvError, channel};
+use std::thread::JoinHandle;
+
+use crate::cartridge::Cartridge;
+use crate::nes::{HostSystem, Nes};
+
+pub enum NesCommand {
+  Pause,
+  Resume,
+  Reset,
+  SwapCartridge(Cartridge),
+  Shutdown,
+}
+
+/// A `Nes` running on its own thread, controlled via `NesCommand`s sent
+/// through an mpsc channel instead of shared mutable state.
+pub struct SharedNes {
+  handle: Option<JoinHandle<()>>,
+  commands: Sender<NesCommand>,
+}
+
+impl SharedNes {
+  /// Spawns a `Nes` for `cartridge` onto a dedicated thread that calls
+  /// `run_until_vblank` in a loop, applying any pending `NesCommand`s
+  /// between frames. `host` must be `Send` since it's moved onto that
+  /// thread; the `Nes` built from it never leaves.
+  pub fn spawn<H: HostSystem + Send + 'static>(cartridge: Cartridge, host: H) -> Self {
+    let (tx, rx) = channel();
+
+    let handle = std::thread::spawn(move || {
+      let nes = Nes::insert(cartridge, host);
+      Self::run(nes, rx);
+    });
+
+    Self { handle: Some(handle), commands: tx }
+  }
+
+  fn run(mut nes: Nes, commands: Receiver<NesCommand>) {
+    let mut paused = false;
+
+    loop {
+      if paused {
+        // Nothing to render while paused, so block instead of spinning on
+        // `try_recv` until a command actually wakes this thread back up.
+        match commands.recv() {
+          Ok(command) => {
+            if !Self::apply(&mut nes, &mut paused, command) {
+              return;
+            }
+          }
+          Err(_) => return,
+        }
+        continue;
+      }
+
+      match commands.try_recv() {
+        Ok(command) => {
+          if !Self::apply(&mut nes, &mut paused, command) {
+            return;
+          }
+        }
+        Err(TryRecvError::Empty) => {}
+        Err(TryRecvError::Disconnected) => return,
+      }
+
+      if !paused {
+        nes.run_until_vblank();
+        if !nes.powered_on() {
+          return;
+        }
+      }
+    }
+  }
+
+  /// Applies one `NesCommand`, returning `false` on `Shutdown` so `run`
+  /// knows to stop the loop.
+  fn apply(nes: &mut Nes, paused: &mut bool, command: NesCommand) -> bool {
+    match command {
+      NesCommand::Pause => *paused = true,
+      NesCommand::Resume => *paused = false,
+      NesCommand::Reset => nes.reset(),
+      NesCommand::SwapCartridge(cartridge) => nes.swap_cartridge(cartridge),
+      NesCommand::Shutdown => return false,
+    }
+    true
+  }
+
+  pub fn pause(&self) {
+    let _ = self.commands.send(NesCommand::Pause);
+  }
+
+  pub fn resume(&self) {
+    let _ = self.commands.send(NesCommand::Resume);
+  }
+
+  pub fn reset(&self) {
+    let _ = self.commands.send(NesCommand::Reset);
+  }
+
+  pub fn swap_cartridge(&self, cartridge: Cartridge) {
+    let _ = self.commands.send(NesCommand::SwapCartridge(cartridge));
+  }
+}
+
+impl Drop for SharedNes {
+  fn drop(&mut self) {
+    let _ = self.commands.send(NesCommand::Shutdown);
+    if let Some(handle) = self.handle.take() {
+      let _ = handle.join();
+    }
+  }
+}
@@ -0,0 +1,31 @@
+
+// Env-var-gated tracing: `trace!(Tag::Cpu, "...", args)` only formats and
+// prints when the matching env var is set, checked at runtime on every
+// call, so it can stay in the tick loop without a logging crate dependency.
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Tag {
+  Cpu,
+  PpuTiming,
+}
+
+impl Tag {
+  fn env_var(self) -> &'static str {
+    match self {
+      Tag::Cpu => "TRACE_CPU",
+      Tag::PpuTiming => "TRACE_PPU_TIMING",
+    }
+  }
+
+  pub fn enabled(self) -> bool {
+    std::env::var(self.env_var()).is_ok()
+  }
+}
+
+macro_rules! trace {
+  ($tag:expr, $($arg:tt)*) => {
+    if $tag.enabled() {
+      println!($($arg)*);
+    }
+  };
+}
@@ -0,0 +1,51 @@
+
+// `embedded-graphics` integration for the pong frontend's RGB332
+// `DisplayBuffer`, mirroring the `nes` crate's `EgHost` so the same custom
+// machine can target a real `DrawTarget` instead of only an SDL texture.
+
+use embedded_graphics::pixelcolor::raw::RawU8;
+use embedded_graphics::pixelcolor::{Rgb332, Rgb888};
+use embedded_graphics::prelude::*;
+use embedded_graphics::Pixel;
+
+use crate::{DisplayBuffer, HEIGHT, WIDTH};
+
+impl OriginDimensions for DisplayBuffer {
+  fn size(&self) -> Size {
+    Size::new(WIDTH as u32, HEIGHT as u32)
+  }
+}
+
+/// Iterates the RGB332 buffer as embedded-graphics `Rgb888` pixels.
+pub struct DisplayPixels<'a> {
+  buffer: &'a DisplayBuffer,
+  next: usize,
+}
+
+impl<'a> DisplayPixels<'a> {
+  pub fn new(buffer: &'a DisplayBuffer) -> Self {
+    Self { buffer, next: 0 }
+  }
+}
+
+impl<'a> Iterator for DisplayPixels<'a> {
+  type Item = Pixel<Rgb888>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.next >= WIDTH * HEIGHT {
+      return None;
+    }
+
+    let x = self.next % WIDTH;
+    let y = self.next / WIDTH;
+    let raw = self.buffer.buffer[self.next];
+    self.next += 1;
+
+    Some(Pixel(Point::new(x as i32, y as i32), Rgb332::from(RawU8::new(raw)).into()))
+  }
+}
+
+/// Blits the display buffer into any `DrawTarget<Color = Rgb888>`.
+pub fn blit<D: DrawTarget<Color = Rgb888>>(buffer: &DisplayBuffer, target: &mut D) -> Result<(), D::Error> {
+  target.draw_iter(DisplayPixels::new(buffer))
+}
@@ -6,11 +6,15 @@ use std::time::Duration;
 use mos6502::cpu::Cpu;
 use mos6502::memory::Bus;
 use mos6502::mos6502::Mos6502;
+use monitor::Monitor;
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
 use sdl2::pixels::PixelFormatEnum;
 use structopt::StructOpt;
 
+#[cfg(feature = "embedded-graphics")]
+mod eg;
+
 enum MemoryMap {
   Ram,
   Display,
@@ -25,6 +29,14 @@ enum DisplayPort {
   PortY = 1,
   PortColor = 2,
   PortCommand = 3,
+  PortX2 = 4,
+  PortY2 = 5,
+  PortSpriteAddrLo = 6,
+  PortSpriteAddrHi = 7,
+  PortSpriteWidth = 8,
+  PortSpriteHeight = 9,
+  PortPaletteIndex = 10,
+  PortPaletteValue = 11,
 }
 
 impl DisplayPort {
@@ -34,6 +46,14 @@ impl DisplayPort {
       1 => DisplayPort::PortY,
       2 => DisplayPort::PortColor,
       3 => DisplayPort::PortCommand,
+      4 => DisplayPort::PortX2,
+      5 => DisplayPort::PortY2,
+      6 => DisplayPort::PortSpriteAddrLo,
+      7 => DisplayPort::PortSpriteAddrHi,
+      8 => DisplayPort::PortSpriteWidth,
+      9 => DisplayPort::PortSpriteHeight,
+      10 => DisplayPort::PortPaletteIndex,
+      11 => DisplayPort::PortPaletteValue,
       _ => panic!("invalid display port"),
     }
   }
@@ -44,6 +64,9 @@ enum DisplayCommand {
   Draw = 1,
   Clear = 2,
   Flush = 3,
+  FillRect = 4,
+  DrawLine = 5,
+  Blit = 6,
 }
 
 impl DisplayCommand {
@@ -53,67 +76,194 @@ impl DisplayCommand {
       1 => DisplayCommand::Draw,
       2 => DisplayCommand::Clear,
       3 => DisplayCommand::Flush,
+      4 => DisplayCommand::FillRect,
+      5 => DisplayCommand::DrawLine,
+      6 => DisplayCommand::Blit,
       _ => panic!("invalid display command"),
     }
   }
 }
 
+// The standard NES palette, approximated as RGB332 bytes. It fills the first
+// 64 entries of the default palette; entries 64-255 default to an identity
+// mapping, so color indices 64-255 are shown as the RGB332 byte of the
+// same value.
+const NES_PALETTE_RGB332: [u8; 64] = [
+  0x6d, 0x03, 0x13, 0x51, 0x90, 0xa0, 0xa0, 0x60, 0x40, 0x20, 0x20, 0x21, 0x29, 0x00, 0x00, 0x00,
+  0xb6, 0x07, 0x1b, 0xd2, 0xd1, 0xe0, 0xe0, 0xa4, 0x64, 0x24, 0x2c, 0x2d, 0x3d, 0x00, 0x00, 0x00,
+  0xff, 0x5f, 0x5e, 0xde, 0xfd, 0xfc, 0xfc, 0xf8, 0xa9, 0x6d, 0x6f, 0x77, 0x7f, 0x00, 0x00, 0x00,
+  0xff, 0xbf, 0xbb, 0xfe, 0xfe, 0xfd, 0xfd, 0xfd, 0xf5, 0xf6, 0xb6, 0xbf, 0xbf, 0x00, 0x00, 0x00,
+];
+
+// Pixel data a Blit command needs to pull straight from ROM/RAM through the
+// bus; `PongBus` fetches it (it owns the rom/ram handles DisplayBuffer
+// doesn't) and feeds it back in via `DisplayBuffer::blit`.
+struct BlitRequest {
+  x: u8,
+  y: u8,
+  address: u16,
+  width: u8,
+  height: u8,
+}
+
 struct DisplayBuffer {
   pub buffer: [u8; WIDTH * HEIGHT],
+  back_buffer: [u8; WIDTH * HEIGHT],
   port_x: u8,
   port_y: u8,
+  port_x2: u8,
+  port_y2: u8,
   port_color: u8,
   port_command: u8,
+  sprite_addr: u16,
+  sprite_width: u8,
+  sprite_height: u8,
+  palette_index: u8,
+  palette: [u8; 256],
   was_updated: bool,
 }
 
 impl DisplayBuffer {
   fn new() -> Self {
-    let buffer = [0; WIDTH * HEIGHT];
+    let mut palette = [0u8; 256];
+    for (i, color) in palette.iter_mut().enumerate() {
+      *color = NES_PALETTE_RGB332.get(i).copied().unwrap_or(i as u8);
+    }
+
     Self {
-      buffer,
+      buffer: [0; WIDTH * HEIGHT],
+      back_buffer: [0; WIDTH * HEIGHT],
       port_x: 0,
       port_y: 0,
+      port_x2: 0,
+      port_y2: 0,
       port_color: 0,
       port_command: 0,
+      sprite_addr: 0,
+      sprite_width: 0,
+      sprite_height: 0,
+      palette_index: 0,
+      palette,
       was_updated: false,
     }
   }
 
-  fn read8(&self, address: u16) -> u8 {
+  fn read8(&self, _address: u16) -> u8 {
     panic!("cannot read from display buffer")
   }
 
-  fn write8(&mut self, val: u8, address: u16) {
+  /// Handles a write to a display port. Returns `Some(BlitRequest)` when
+  /// the write triggered a `Blit` command, since reading the sprite's
+  /// pixel data requires going back through the bus that owns ROM/RAM.
+  fn write8(&mut self, val: u8, address: u16) -> Option<BlitRequest> {
     match DisplayPort::from_u16(address) {
       DisplayPort::PortX => self.port_x = val,
       DisplayPort::PortY => self.port_y = val,
       DisplayPort::PortColor => self.port_color = val,
       DisplayPort::PortCommand => self.port_command = val,
+      DisplayPort::PortX2 => self.port_x2 = val,
+      DisplayPort::PortY2 => self.port_y2 = val,
+      DisplayPort::PortSpriteAddrLo => self.sprite_addr = (self.sprite_addr & 0xFF00) | val as u16,
+      DisplayPort::PortSpriteAddrHi => self.sprite_addr = (self.sprite_addr & 0x00FF) | ((val as u16) << 8),
+      DisplayPort::PortSpriteWidth => self.sprite_width = val,
+      DisplayPort::PortSpriteHeight => self.sprite_height = val,
+      DisplayPort::PortPaletteIndex => self.palette_index = val,
+      DisplayPort::PortPaletteValue => self.palette[self.palette_index as usize] = val,
     }
 
-    match DisplayCommand::from_u8(self.port_command) {
-      DisplayCommand::Draw => self.draw(),
-      DisplayCommand::Flush => self.was_updated = true,
-      DisplayCommand::Clear => self.clear(),
-      _ => {}
-    }
+    let blit_request = match DisplayCommand::from_u8(self.port_command) {
+      DisplayCommand::Draw => { self.draw(); None }
+      DisplayCommand::Flush => { self.flush(); None }
+      DisplayCommand::Clear => { self.clear(); None }
+      DisplayCommand::FillRect => { self.fill_rect(); None }
+      DisplayCommand::DrawLine => { self.draw_line(); None }
+      DisplayCommand::Blit => Some(BlitRequest {
+        x: self.port_x,
+        y: self.port_y,
+        address: self.sprite_addr,
+        width: self.sprite_width,
+        height: self.sprite_height,
+      }),
+      DisplayCommand::Nop => None,
+    };
 
     self.port_command = 0;
+    blit_request
+  }
+
+  fn color(&self) -> u8 {
+    self.palette[self.port_color as usize]
+  }
+
+  fn put_pixel(&mut self, x: usize, y: usize, color: u8) {
+    if x < WIDTH && y < HEIGHT {
+      self.back_buffer[y * WIDTH + x] = color;
+    }
   }
 
   fn draw(&mut self) {
-    let x = self.port_x as usize;
-    let y = self.port_y as usize;
-    let color = self.port_color;
-    self.buffer[y * WIDTH + x] = color;
+    let color = self.color();
+    self.put_pixel(self.port_x as usize, self.port_y as usize, color);
   }
 
   fn clear(&mut self) {
-    self.buffer = [0; WIDTH * HEIGHT];
+    self.back_buffer = [0; WIDTH * HEIGHT];
+  }
+
+  fn fill_rect(&mut self) {
+    let (x0, x1) = (self.port_x.min(self.port_x2), self.port_x.max(self.port_x2));
+    let (y0, y1) = (self.port_y.min(self.port_y2), self.port_y.max(self.port_y2));
+    let color = self.color();
+    for y in y0..=y1 {
+      for x in x0..=x1 {
+        self.put_pixel(x as usize, y as usize, color);
+      }
+    }
+  }
+
+  // Bresenham's line algorithm between (port_x, port_y) and (port_x2, port_y2).
+  fn draw_line(&mut self) {
+    let (mut x0, mut y0) = (self.port_x as i32, self.port_y as i32);
+    let (x1, y1) = (self.port_x2 as i32, self.port_y2 as i32);
+    let color = self.color();
+
+    let dx = (x1 - x0).abs();
+    let dy = -(y1 - y0).abs();
+    let sx = if x0 < x1 { 1 } else { -1 };
+    let sy = if y0 < y1 { 1 } else { -1 };
+    let mut err = dx + dy;
+
+    loop {
+      self.put_pixel(x0 as usize, y0 as usize, color);
+      if x0 == x1 && y0 == y1 {
+        break;
+      }
+      let e2 = 2 * err;
+      if e2 >= dy {
+        err += dy;
+        x0 += sx;
+      }
+      if e2 <= dx {
+        err += dx;
+        y0 += sy;
+      }
+    }
+  }
+
+  /// Blits `pixels` (one RGB332 byte per pixel, `width * height` of them,
+  /// read straight from ROM/RAM by the caller) at (x, y).
+  fn blit(&mut self, x: u8, y: u8, width: u8, height: u8, pixels: &[u8]) {
+    for row in 0..height as usize {
+      for col in 0..width as usize {
+        if let Some(&color) = pixels.get(row * width as usize + col) {
+          self.put_pixel(x as usize + col, y as usize + row, color);
+        }
+      }
+    }
   }
 
   fn flush(&mut self) {
+    std::mem::swap(&mut self.buffer, &mut self.back_buffer);
     self.was_updated = true;
   }
 
@@ -187,7 +337,16 @@ impl Bus for PongBus {
     let (memory_map, mapped_address) = self.map_address(address);
     match memory_map {
       MemoryMap::Ram => self.ram[mapped_address as usize] = val,
-      MemoryMap::Display => self.display.borrow_mut().write8(val, mapped_address),
+      MemoryMap::Display => {
+        let blit_request = self.display.borrow_mut().write8(val, mapped_address);
+        if let Some(request) = blit_request {
+          let pixel_count = request.width as usize * request.height as usize;
+          let pixels: Vec<u8> = (0..pixel_count as u16)
+            .map(|offset| self.read8(request.address.wrapping_add(offset)))
+            .collect();
+          self.display.borrow_mut().blit(request.x, request.y, request.width, request.height, &pixels);
+        }
+      }
       MemoryMap::Rom => panic!("cannot write to ROM"),
     }
   }
@@ -211,12 +370,17 @@ fn main() {
   cpu.reset();
   let mut machine = Mos6502::new(cpu);
   machine.debugger().verbose(args.verbose);
-  if args.debug {
+
+  let mut monitor = Monitor::new();
+  // `Some(0)` drops straight into the monitor prompt on the first
+  // instruction when `--debug` is passed; `None` means free-run until the
+  // next breakpoint.
+  let mut pending_steps: Option<usize> = if args.debug {
     machine.debugger().enable();
-    machine.debugger().watch_memory_range(0..=5, |mem| {
-      println!("watched memory range: {:?}", mem);
-    });
-  }
+    Some(0)
+  } else {
+    None
+  };
 
   let sdl_context = sdl2::init().unwrap();
   let video_subsystem = sdl_context.video().unwrap();
@@ -250,6 +414,24 @@ fn main() {
       }
     }
 
+    if args.debug {
+      if monitor.trace_only() {
+        monitor.trace(machine.cpu(), machine.bus().as_ref());
+      }
+
+      let pc = machine.cpu().pc();
+      let should_stop = monitor.has_breakpoint(pc) || pending_steps == Some(0);
+      if should_stop {
+        let steps = monitor.repl(machine.cpu_mut(), machine.bus_mut().as_mut());
+        // `steps` instructions should run before the monitor prompt comes
+        // back; the `machine.tick()` below already executes the first one,
+        // so only `steps - 1` are left to count down.
+        pending_steps = if steps == 0 { None } else { Some(steps - 1) };
+      } else if let Some(remaining) = pending_steps.as_mut() {
+        *remaining -= 1;
+      }
+    }
+
     machine.tick();
 
     if display_buffer.borrow_mut().was_updated() {